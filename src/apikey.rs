@@ -7,6 +7,165 @@ use uuid::Uuid;
 
 use crate::error::{RestError, Result};
 
+/// Environment variable read by [`ApiKey::from_env`] for the key id.
+pub const ENV_API_KEY_ID: &str = "KLBFW_API_KEY_ID";
+/// Environment variable read by [`ApiKey::from_env`] for the secret.
+pub const ENV_API_KEY_SECRET: &str = "KLBFW_API_KEY_SECRET";
+
+/// Authentication parameters produced by [`ApiKey::sign_request`].
+///
+/// These are the `_key`, `_time`, `_nonce` and `_sign` values the server
+/// expects, or the same under the key's
+/// [custom names](ApiKey::with_param_names); attach them as query parameters
+/// to whatever transport carries the request (HTTP, a WebSocket upgrade,
+/// ...).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedParams {
+    /// API key identifier (`_key`)
+    pub key: String,
+    /// Unix timestamp in seconds (`_time`)
+    pub time: String,
+    /// Random nonce (`_nonce`)
+    pub nonce: String,
+    /// Base64url-encoded Ed25519 signature (`_sign`)
+    pub sign: String,
+    /// Names the parameters were signed under
+    pub names: ParamNames,
+}
+
+/// Names of the query parameters carrying an API key signature
+///
+/// Defaults to `_key`, `_time`, `_nonce` and `_sign`. Set other names with
+/// [`ApiKey::with_param_names`] for gateways or API versions that rename
+/// them:
+///
+/// ```
+/// use klbfw::apikey::ParamNames;
+///
+/// let names = ParamNames {
+///     sign: "_signature".to_string(),
+///     ..ParamNames::default()
+/// };
+/// assert_eq!(names.key, "_key");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamNames {
+    /// API key identifier
+    pub key: String,
+    /// Unix timestamp
+    pub time: String,
+    /// Random nonce
+    pub nonce: String,
+    /// Signature, left out of the signed query string
+    pub sign: String,
+}
+
+impl ParamNames {
+    /// The names of the parameters that change on every request: time,
+    /// nonce and signature.
+    pub(crate) fn volatile(&self) -> [&str; 3] {
+        [&self.time, &self.nonce, &self.sign]
+    }
+}
+
+impl Default for ParamNames {
+    fn default() -> Self {
+        ParamNames {
+            key: "_key".to_string(),
+            time: "_time".to_string(),
+            nonce: "_nonce".to_string(),
+            sign: "_sign".to_string(),
+        }
+    }
+}
+
+/// What an API key signature covers, for debugging signature mismatches
+///
+/// The signature is over the method, the API path, the canonical query string
+/// and the SHA-256 of the body, joined by NUL bytes (see
+/// [`to_bytes`](Self::to_bytes)). Where the request parameters end up depends
+/// on the method, so the same parameters sign differently:
+/// - `GET`, `HEAD` and `OPTIONS` send them as JSON in the `_` query parameter
+///   (or as plain query pairs with
+///   [`QueryEncoding::Repeated`](crate::QueryEncoding::Repeated)), which the
+///   query string covers, and have an empty body
+/// - `PUT`, `POST` and `PATCH` send them as the JSON body, covered by its
+///   hash; the query string then only has the `_key`, `_time` and `_nonce`
+///   parameters
+///
+/// Get one from [`ApiKey::signing_input`] or
+/// [`Client::signing_input`](crate::Client::signing_input).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SigningInput {
+    /// HTTP method
+    pub method: String,
+    /// API endpoint path
+    pub path: String,
+    /// Every query parameter except `_sign`, sorted by name (repeated names
+    /// keep their order) and form-urlencoded
+    pub query: String,
+    /// SHA-256 digest of the request body
+    pub body_sha256: Vec<u8>,
+}
+
+impl SigningInput {
+    /// The exact bytes that are signed
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(self.method.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(self.path.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(self.query.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(&self.body_sha256);
+        bytes
+    }
+}
+
+impl std::fmt::Display for SigningInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hash: String = self
+            .body_sha256
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        writeln!(f, "method: {}", self.method)?;
+        writeln!(f, "path: {}", self.path)?;
+        writeln!(f, "query: {}", self.query)?;
+        write!(f, "body_sha256: {}", hash)
+    }
+}
+
+impl SignedParams {
+    /// The parameters as `(name, value)` pairs, in key, time, nonce,
+    /// signature order, under the [`names`](Self::names) they were signed
+    /// with.
+    pub fn pairs(&self) -> [(&str, &str); 4] {
+        self.pairs_named(&self.names)
+    }
+
+    /// The parameters as `(name, value)` pairs named after `names`, in key,
+    /// time, nonce, signature order.
+    pub(crate) fn pairs_named<'a>(&'a self, names: &'a ParamNames) -> [(&'a str, &'a str); 4] {
+        [
+            (&names.key, &self.key),
+            (&names.time, &self.time),
+            (&names.nonce, &self.nonce),
+            (&names.sign, &self.sign),
+        ]
+    }
+
+    /// Insert the parameters into a query parameter map, under the names
+    /// they were signed with.
+    pub fn apply_to(&self, params: &mut HashMap<String, String>) {
+        for (name, value) in self.pairs() {
+            params.insert(name.to_string(), value.to_string());
+        }
+    }
+}
+
 /// ApiKey represents an API key with its secret for signing requests.
 #[derive(Clone)]
 pub struct ApiKey {
@@ -14,6 +173,8 @@ pub struct ApiKey {
     pub key_id: String,
     /// Ed25519 private key (seed) for signing
     private_key: Ed25519PrivateKey,
+    /// Names of the signature parameters
+    param_names: ParamNames,
 }
 
 impl ApiKey {
@@ -48,97 +209,261 @@ impl ApiKey {
         Ok(ApiKey {
             key_id,
             private_key,
+            param_names: ParamNames::default(),
         })
     }
 
+    /// Load the key from the `KLBFW_API_KEY_ID` and `KLBFW_API_KEY_SECRET`
+    /// environment variables
+    ///
+    /// Fails naming the variable when one is missing or the secret does not
+    /// decode.
+    pub fn from_env() -> Result<Self> {
+        Self::from_env_named(ENV_API_KEY_ID, ENV_API_KEY_SECRET)
+    }
+
+    /// Load the key from the environment variables `id_var` and
+    /// `secret_var`
+    ///
+    /// Same as [`from_env`](Self::from_env), with custom variable names.
+    pub fn from_env_named(id_var: &str, secret_var: &str) -> Result<Self> {
+        let var = |name: &str| {
+            std::env::var(name)
+                .map_err(|e| RestError::Other(format!("environment variable {}: {}", name, e)))
+        };
+        let key_id = var(id_var)?;
+        let secret = var(secret_var)?;
+        Self::new(key_id, secret.trim()).map_err(|e| {
+            RestError::Other(format!(
+                "invalid API key secret in environment variable {}: {}",
+                secret_var, e
+            ))
+        })
+    }
+
+    /// Send the signature under other parameter names (builder style)
+    ///
+    /// The names are used for every request signed with this key, and the
+    /// `sign` name is the one left out of the signed query string. Clients
+    /// using the key mask the renamed signature in traces, curl commands and
+    /// error reports, and leave the renamed parameters out of cache keys and
+    /// recordings.
+    pub fn with_param_names(mut self, names: ParamNames) -> Self {
+        self.param_names = names;
+        self
+    }
+
+    /// Names of the signature parameters
+    pub fn param_names(&self) -> &ParamNames {
+        &self.param_names
+    }
+
     /// Generate a signature for a REST API request
     ///
+    /// The signed string covers the method, path, every query parameter except
+    /// `_sign` (sorted by name; repeated names keep their relative order), and
+    /// the SHA-256 of the body. Most callers want
+    /// [`sign_request`](Self::sign_request), which also fills in the
+    /// `_key`/`_time`/`_nonce` parameters this signature is expected to cover.
+    ///
     /// # Arguments
     /// * `method` - HTTP method (GET, POST, etc.)
     /// * `path` - API endpoint path
     /// * `query_params` - Query parameters as key-value pairs
     /// * `body` - Request body bytes (if any)
-    fn generate_signature(
+    pub fn generate_signature(
         &self,
         method: &str,
         path: &str,
         query_params: &HashMap<String, String>,
         body: &[u8],
     ) -> Result<String> {
-        // Generate SHA256 hash of the request body
-        let body_hash = sha256(body);
-
-        // Build query string (excluding _sign parameter)
-        let mut params: Vec<(String, String)> = query_params
-            .iter()
-            .filter(|(k, _)| k.as_str() != "_sign")
-            .map(|(k, v)| (k.clone(), v.clone()))
-            .collect();
+        self.generate_signature_with_hash(method, path, query_params, &sha256(body))
+    }
 
-        // Sort parameters for consistent ordering
-        params.sort_by(|a, b| a.0.cmp(&b.0));
+    /// Generate a signature from a precomputed SHA-256 digest of the body
+    ///
+    /// Same as [`generate_signature`](Self::generate_signature), for bodies
+    /// too large to hold in memory: hash the body while streaming it, then
+    /// sign with the 32-byte digest. Any other digest length is an error.
+    ///
+    /// # Arguments
+    /// * `method` - HTTP method (GET, POST, etc.)
+    /// * `path` - API endpoint path
+    /// * `query_params` - Query parameters as key-value pairs
+    /// * `body_sha256` - Raw (not hex) SHA-256 digest of the request body
+    pub fn generate_signature_with_hash(
+        &self,
+        method: &str,
+        path: &str,
+        query_params: &HashMap<String, String>,
+        body_sha256: &[u8],
+    ) -> Result<String> {
+        if body_sha256.len() != 32 {
+            return Err(RestError::Other(format!(
+                "body digest must be 32 bytes, got {}",
+                body_sha256.len()
+            )));
+        }
+        Ok(self.pairs_signature(method, path, query_params, body_sha256))
+    }
 
-        let query_string: String = form_urlencoded::Serializer::new(String::new())
-            .extend_pairs(params)
-            .finish();
+    /// What [`generate_signature`](Self::generate_signature) signs for the
+    /// same arguments
+    pub fn signing_input(
+        &self,
+        method: &str,
+        path: &str,
+        query_params: &HashMap<String, String>,
+        body: &[u8],
+    ) -> SigningInput {
+        pairs_input(
+            method,
+            path,
+            query_params,
+            &sha256(body),
+            &self.param_names.sign,
+        )
+    }
 
-        // Build signing string with null byte separators
-        let mut sign_string = Vec::new();
-        sign_string.extend_from_slice(method.as_bytes());
-        sign_string.push(0);
-        sign_string.extend_from_slice(path.as_bytes());
-        sign_string.push(0);
-        sign_string.extend_from_slice(query_string.as_bytes());
-        sign_string.push(0);
-        sign_string.extend_from_slice(&body_hash);
+    /// Signature over query parameters given as `(name, value)` pairs, which
+    /// may repeat a name, and the SHA-256 digest of the body.
+    fn pairs_signature<'a>(
+        &self,
+        method: &str,
+        path: &str,
+        query_params: impl IntoIterator<Item = (&'a String, &'a String)>,
+        body_hash: &[u8],
+    ) -> String {
+        let input = pairs_input(
+            method,
+            path,
+            query_params,
+            body_hash,
+            &self.param_names.sign,
+        );
 
         // Sign using Ed25519
-        let signature = self.private_key.sign(&sign_string);
+        let signature = self.private_key.sign(&input.to_bytes());
 
         // Encode signature as base64url
-        let encoded = URL_SAFE_NO_PAD.encode(signature.to_bytes());
-
-        Ok(encoded)
+        URL_SAFE_NO_PAD.encode(signature.to_bytes())
     }
 
-    /// Apply API key parameters to query parameters
+    /// Sign a request, returning the authentication parameters to attach.
     ///
-    /// Adds _key, _time, _nonce, and _sign parameters
-    pub(crate) fn apply_params(
+    /// `query` holds the request's other query parameters (they are covered by
+    /// the signature but not included in the result). The result carries the
+    /// key's [parameter names](Self::with_param_names), so
+    /// [`SignedParams::apply_to`] and [`SignedParams::pairs`] attach it under
+    /// the names it was signed with. This is the transport
+    /// agnostic counterpart of what [`Client`](crate::Client) does for every
+    /// HTTP request, usable for WebSocket upgrades and similar.
+    ///
+    /// # Arguments
+    /// * `method` - HTTP method (GET, POST, etc.)
+    /// * `path` - API endpoint path
+    /// * `query` - Other query parameters sent with the request
+    /// * `body` - Request body bytes (if any)
+    pub fn sign_request(
         &self,
         method: &str,
         path: &str,
-        params: &mut HashMap<String, String>,
+        query: &HashMap<String, String>,
         body: &[u8],
-    ) -> Result<()> {
-        // Add API key parameters
-        params.insert("_key".to_string(), self.key_id.clone());
+    ) -> Result<SignedParams> {
+        let mut signed = self.unsigned_params()?;
+
+        // The signature covers the auth parameters too, so sign over the
+        // caller's query with them merged in.
+        let mut params = query.clone();
+        signed.apply_to(&mut params);
+        signed.sign = self.generate_signature(method, path, &params, body)?;
+
+        Ok(signed)
+    }
 
-        // Add timestamp
+    /// Fresh key, time and nonce parameters, with an empty signature.
+    fn unsigned_params(&self) -> Result<SignedParams> {
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map_err(|e| RestError::Other(format!("system clock before unix epoch: {}", e)))?
             .as_secs();
-        params.insert("_time".to_string(), timestamp.to_string());
 
-        // Add nonce
-        let nonce = Uuid::new_v4().to_string();
-        params.insert("_nonce".to_string(), nonce);
-
-        // Generate and add signature
-        let signature = self.generate_signature(method, path, params, body)?;
-        params.insert("_sign".to_string(), signature);
+        Ok(SignedParams {
+            key: self.key_id.clone(),
+            time: timestamp.to_string(),
+            nonce: Uuid::new_v4().to_string(),
+            sign: String::new(),
+            names: self.param_names.clone(),
+        })
+    }
 
+    /// Apply API key parameters to query parameters
+    ///
+    /// Adds the key, time, nonce and signature parameters (`_key`, `_time`,
+    /// `_nonce` and `_sign` unless renamed). `params` may repeat a
+    /// name; see [`generate_signature`](Self::generate_signature) for how
+    /// such parameters are signed.
+    pub(crate) fn apply_params(
+        &self,
+        method: &str,
+        path: &str,
+        params: &mut Vec<(String, String)>,
+        body: &[u8],
+    ) -> Result<()> {
+        let mut signed = self.unsigned_params()?;
+        params.extend(
+            signed.pairs()[..3]
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string())),
+        );
+        let pairs = params.iter().map(|(k, v)| (k, v));
+        signed.sign = self.pairs_signature(method, path, pairs, &sha256(body));
+        params.push((self.param_names.sign.clone(), signed.sign));
         Ok(())
     }
 }
 
+/// The signing input of query parameters given as `(name, value)` pairs, which
+/// may repeat a name, and the SHA-256 digest of the body. The signature
+/// parameter `sign_param` is left out.
+pub(crate) fn pairs_input<'a>(
+    method: &str,
+    path: &str,
+    query_params: impl IntoIterator<Item = (&'a String, &'a String)>,
+    body_hash: &[u8],
+    sign_param: &str,
+) -> SigningInput {
+    // Build query string (excluding the signature parameter)
+    let mut params: Vec<(&String, &String)> = query_params
+        .into_iter()
+        .filter(|(k, _)| k.as_str() != sign_param)
+        .collect();
+
+    // Sort parameters for consistent ordering. The sort is stable, so
+    // repeated names stay in the order they are sent.
+    params.sort_by(|a, b| a.0.cmp(b.0));
+
+    let query = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(params)
+        .finish();
+
+    SigningInput {
+        method: method.to_string(),
+        path: path.to_string(),
+        query,
+        body_sha256: body_hash.to_vec(),
+    }
+}
+
 // Implement Debug manually to avoid exposing the secret key
 impl std::fmt::Debug for ApiKey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ApiKey")
             .field("key_id", &self.key_id)
             .field("private_key", &"<redacted>")
+            .field("param_names", &self.param_names)
             .finish()
     }
 }
@@ -185,5 +510,111 @@ mod tests {
             .generate_signature("GET", "Test/Path", &params, b"body")
             .unwrap();
         assert_eq!(sig, sig2);
+
+        // A body hashed incrementally signs the same as the whole body.
+        use purecrypto::hash::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+        hasher.update(b"bo");
+        hasher.update(b"dy");
+        let digest = hasher.finalize();
+        let sig3 = key
+            .generate_signature_with_hash("GET", "Test/Path", &params, &digest)
+            .unwrap();
+        assert_eq!(sig, sig3);
+        assert!(key
+            .generate_signature_with_hash("GET", "Test/Path", &params, b"short")
+            .is_err());
+    }
+
+    #[test]
+    fn test_repeated_params_signature() {
+        let secret = URL_SAFE_NO_PAD.encode([7u8; 32]);
+        let key = ApiKey::new("test-key".to_string(), &secret).unwrap();
+        let pair = |k: &str, v: &str| (k.to_string(), v.to_string());
+
+        // Names are sorted, repeated ones keep their order.
+        let params = [pair("ids", "2"), pair("a", "x"), pair("ids", "1")];
+        let sorted = [pair("a", "x"), pair("ids", "2"), pair("ids", "1")];
+        let swapped = [pair("a", "x"), pair("ids", "1"), pair("ids", "2")];
+        let sign = |p: &[(String, String)]| {
+            key.pairs_signature(
+                "GET",
+                "Test/Path",
+                p.iter().map(|(k, v)| (k, v)),
+                &sha256(b""),
+            )
+        };
+        assert_eq!(sign(&params), sign(&sorted));
+        assert_ne!(sign(&params), sign(&swapped));
+
+        let mut params = params.to_vec();
+        key.apply_params("GET", "Test/Path", &mut params, b"")
+            .unwrap();
+        assert_eq!(params.len(), 7);
+        assert_eq!(params[6].0, "_sign");
+        assert_eq!(params[6].1, sign(&params));
+    }
+
+    #[test]
+    fn test_sign_request() {
+        let secret = URL_SAFE_NO_PAD.encode([7u8; 32]);
+        let key = ApiKey::new("test-key".to_string(), &secret).unwrap();
+
+        let mut query = HashMap::new();
+        query.insert("foo".to_string(), "bar".to_string());
+
+        let signed = key.sign_request("GET", "Test/Path", &query, b"").unwrap();
+        assert_eq!(signed.key, "test-key");
+        assert!(!signed.nonce.is_empty());
+
+        // The signature covers the caller's query plus the auth parameters.
+        let mut params = query.clone();
+        signed.apply_to(&mut params);
+        let expected = key
+            .generate_signature("GET", "Test/Path", &params, b"")
+            .unwrap();
+        assert_eq!(signed.sign, expected);
+        assert_eq!(params.len(), 5);
+
+        // Custom names are kept with the parameters they signed.
+        let names = ParamNames {
+            sign: "_signature".to_string(),
+            time: "_ts".to_string(),
+            ..ParamNames::default()
+        };
+        let key = key.with_param_names(names.clone());
+        let signed = key.sign_request("GET", "Test/Path", &query, b"").unwrap();
+        assert_eq!(signed.names, names);
+        let mut params = query.clone();
+        signed.apply_to(&mut params);
+        assert!(params.contains_key("_signature") && params.contains_key("_ts"));
+        assert!(!params.contains_key("_sign") && !params.contains_key("_time"));
+        let expected = key
+            .generate_signature("GET", "Test/Path", &params, b"")
+            .unwrap();
+        assert_eq!(signed.sign, expected);
+    }
+
+    #[test]
+    fn test_from_env_named() {
+        let (id_var, secret_var) = ("KLBFW_TEST_FROM_ENV_ID", "KLBFW_TEST_FROM_ENV_SECRET");
+        let err = ApiKey::from_env_named(id_var, secret_var).unwrap_err();
+        assert!(err.to_string().contains(id_var), "{}", err);
+
+        std::env::set_var(id_var, "key-env");
+        let err = ApiKey::from_env_named(id_var, secret_var).unwrap_err();
+        assert!(err.to_string().contains(secret_var), "{}", err);
+
+        std::env::set_var(secret_var, "not base64!");
+        let err = ApiKey::from_env_named(id_var, secret_var).unwrap_err();
+        assert!(
+            err.to_string().contains("invalid API key secret"),
+            "{}",
+            err
+        );
+
+        std::env::set_var(secret_var, format!("{}\n", "A".repeat(43)));
+        let key = ApiKey::from_env_named(id_var, secret_var).unwrap();
+        assert_eq!(key.key_id, "key-env");
     }
 }
@@ -0,0 +1,382 @@
+//! Non-blocking requests for async code.
+//!
+//! [`AsyncClient`] wraps a [`Client`] and returns futures instead of
+//! blocking the calling thread, so requests can be awaited from an async
+//! runtime such as tokio without `spawn_blocking`:
+//!
+//! ```no_run
+//! use klbfw::async_client::AsyncClient;
+//! use klbfw::Client;
+//!
+//! # async fn run() -> klbfw::Result<()> {
+//! let ctx = AsyncClient::new(Client::new());
+//! let time: serde_json::Value = ctx.apply("Misc/Debug:serverTime", "GET", ()).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The transport stays blocking: each request, token renewal included, runs
+//! to completion on a pool of threads shared by the client and its clones,
+//! which wakes the future when done. The pool starts threads as requests
+//! come, up to 8 by default (see [`AsyncClient::with_max_threads`]); further
+//! requests wait for a free thread. Dropping a future before its request
+//! started cancels it; once started, a request runs to completion. The
+//! methods mirror those of [`Client`] and return the same errors.
+//!
+//! Available with the `async` feature.
+
+use crate::error::{RestError, Result};
+use crate::response::Response;
+use crate::rest::Client;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, Weak};
+use std::task::{Context, Poll, Waker};
+
+/// Default number of threads running the requests of an [`AsyncClient`].
+const DEFAULT_MAX_THREADS: usize = 8;
+
+/// [`Client`] whose requests are awaited instead of blocking
+///
+/// Cloning is cheap and clones share the client's token and transport, and
+/// the threads running their requests.
+#[derive(Debug, Clone)]
+pub struct AsyncClient {
+    client: Client,
+    pool: Arc<Pool>,
+}
+
+impl Default for AsyncClient {
+    fn default() -> Self {
+        AsyncClient::new(Client::default())
+    }
+}
+
+impl AsyncClient {
+    /// Make the requests of `client` without blocking
+    pub fn new(client: Client) -> Self {
+        AsyncClient {
+            client,
+            pool: Arc::new(Pool::new(DEFAULT_MAX_THREADS)),
+        }
+    }
+
+    /// Run up to `max_threads` requests at once (at least one, 8 by default)
+    ///
+    /// Requests made while all threads are busy wait for one to be free. The
+    /// threads are shared with clones made after this call.
+    pub fn with_max_threads(mut self, max_threads: usize) -> Self {
+        self.pool = Arc::new(Pool::new(max_threads.max(1)));
+        self
+    }
+
+    /// The wrapped client, for its blocking methods and configuration
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Execute a REST API request and unmarshal the response data, like
+    /// [`Client::apply`]
+    pub fn apply<T, P>(&self, path: &str, method: &str, param: P) -> Request<T>
+    where
+        T: DeserializeOwned + Send + 'static,
+        P: Serialize,
+    {
+        self.spawn(path, method, param, |client, path, method, param| {
+            client.apply(path, method, param)
+        })
+    }
+
+    /// `GET` an endpoint and unmarshal the response data, like
+    /// [`Client::get`]
+    pub fn get<T>(&self, path: &str) -> Request<T>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        self.apply(path, "GET", serde_json::Map::new())
+    }
+
+    /// `POST` parameters to an endpoint and unmarshal the response data,
+    /// like [`Client::post`]
+    pub fn post<T, P>(&self, path: &str, param: P) -> Request<T>
+    where
+        T: DeserializeOwned + Send + 'static,
+        P: Serialize,
+    {
+        self.apply(path, "POST", param)
+    }
+
+    /// Execute a REST API request and return the raw response, like
+    /// [`Client::do_request`]
+    pub fn do_request<P>(&self, path: &str, method: &str, param: P) -> Request<Response>
+    where
+        P: Serialize,
+    {
+        self.spawn(path, method, param, |client, path, method, param| {
+            client.do_request(path, method, param)
+        })
+    }
+
+    /// Run `call` with the serialized parameters on the client's threads.
+    fn spawn<T, P, F>(&self, path: &str, method: &str, param: P, call: F) -> Request<T>
+    where
+        T: Send + 'static,
+        P: Serialize,
+        F: FnOnce(&Client, &str, &str, serde_json::Value) -> Result<T> + Send + 'static,
+    {
+        // The parameters are borrowed; serialize them before leaving.
+        let param = match serde_json::to_value(param) {
+            Ok(param) => param,
+            Err(e) => return Request::ready(Err(self.client.correlate(e.into()))),
+        };
+        let client = self.client.clone();
+        let (path, method) = (path.to_string(), method.to_string());
+        Request::spawn(&self.pool, move || call(&client, &path, &method, param))
+    }
+}
+
+impl From<Client> for AsyncClient {
+    fn from(client: Client) -> Self {
+        AsyncClient::new(client)
+    }
+}
+
+/// Work sent to the threads of a [`Pool`].
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Threads running the requests of an [`AsyncClient`] and its clones.
+///
+/// Threads are started when a job comes and none is idle, up to
+/// `max_threads`, and stop once the pool is dropped.
+struct Pool {
+    sender: mpsc::Sender<Job>,
+    receiver: Arc<Mutex<mpsc::Receiver<Job>>>,
+    max_threads: usize,
+    threads: AtomicUsize,
+    /// Threads waiting for a job that no job was sent for yet.
+    idle: Arc<AtomicUsize>,
+}
+
+impl Pool {
+    fn new(max_threads: usize) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Pool {
+            sender,
+            receiver: Arc::new(Mutex::new(receiver)),
+            max_threads,
+            threads: AtomicUsize::new(0),
+            idle: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Queue `job`, starting a thread for it if none is idle.
+    fn submit(&self, job: Job) {
+        // The pool keeps the receiver, so sending cannot fail.
+        let _ = self.sender.send(job);
+        let claimed = self
+            .idle
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        let start = !claimed
+            && self
+                .threads
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                    (n < self.max_threads).then_some(n + 1)
+                })
+                .is_ok();
+        if start {
+            let receiver = Arc::clone(&self.receiver);
+            let idle = Arc::clone(&self.idle);
+            std::thread::spawn(move || loop {
+                // Fails once the pool is dropped and the queue drained.
+                let Ok(job) = receiver.lock().unwrap().recv() else {
+                    return;
+                };
+                job();
+                idle.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+    }
+}
+
+impl std::fmt::Debug for Pool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pool")
+            .field("max_threads", &self.max_threads)
+            .field("threads", &self.threads.load(Ordering::SeqCst))
+            .finish()
+    }
+}
+
+/// Result of a request, shared between its thread and its future.
+struct Shared<T> {
+    result: Option<Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// Future of a request made with [`AsyncClient`]
+#[must_use = "futures do nothing unless awaited"]
+pub struct Request<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+impl<T: Send + 'static> Request<T> {
+    /// Run `call` on a thread of `pool`, completing the future with its
+    /// result, unless the future is dropped first.
+    fn spawn(pool: &Pool, call: impl FnOnce() -> Result<T> + Send + 'static) -> Self {
+        let request = Request::new(None);
+        let shared: Weak<Mutex<Shared<T>>> = Arc::downgrade(&request.shared);
+        pool.submit(Box::new(move || {
+            let Some(shared) = shared.upgrade() else {
+                return;
+            };
+            // A panic must neither leave the future pending nor end the thread.
+            let result = std::panic::catch_unwind(AssertUnwindSafe(call))
+                .unwrap_or_else(|_| Err(RestError::Other("request panicked".to_string())));
+            let waker = {
+                let mut shared = shared.lock().unwrap();
+                shared.result = Some(result);
+                shared.waker.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }));
+        request
+    }
+}
+
+impl<T> Request<T> {
+    /// A future already completed with `result`.
+    fn ready(result: Result<T>) -> Self {
+        Request::new(Some(result))
+    }
+
+    fn new(result: Option<Result<T>>) -> Self {
+        Request {
+            shared: Arc::new(Mutex::new(Shared {
+                result,
+                waker: None,
+            })),
+        }
+    }
+}
+
+impl<T> Future for Request<T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.shared.lock().unwrap();
+        match shared.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Request<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let done = self.shared.lock().unwrap().result.is_some();
+        f.debug_struct("Request").field("done", &done).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{HttpRequest, HttpResponse, Transport};
+    use std::task::Wake;
+    use std::thread::Thread;
+    use std::time::Duration;
+
+    /// Wakes a parked thread.
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Run `future` to completion on the current thread.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    /// Answers after a delay, so futures are polled before completing, and
+    /// counts the requests sent and the most in flight at once.
+    #[derive(Default)]
+    struct Gauge {
+        calls: AtomicUsize,
+        active: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    impl Transport for Gauge {
+        fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(active, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(20));
+            self.active.fetch_sub(1, Ordering::SeqCst);
+            let body = if request.url.contains("Missing") {
+                r#"{"result":"error","error":"not found","code":404}"#
+            } else {
+                r#"{"result":"success","data":{"n":1}}"#
+            };
+            Ok(HttpResponse::new(200, Vec::new(), body.as_bytes().to_vec()))
+        }
+    }
+
+    #[test]
+    fn test_async_client() {
+        let gauge = Arc::new(Gauge::default());
+        let ctx = AsyncClient::new(Client::new().with_transport(gauge.clone()));
+
+        let value: serde_json::Value = block_on(ctx.get("Thing")).unwrap();
+        assert_eq!(value, serde_json::json!({"n": 1}));
+        let response = block_on(ctx.do_request("Thing", "POST", ())).unwrap();
+        assert_eq!(response.result, "success");
+        let err = block_on(ctx.do_request("Missing", "GET", ())).unwrap_err();
+        assert_eq!(err.status_code(), Some(404));
+
+        // Requests run concurrently, on no more threads than allowed.
+        let gauge = Arc::new(Gauge::default());
+        let ctx = AsyncClient::new(Client::new().with_transport(gauge.clone())).with_max_threads(2);
+        let requests: Vec<_> = (0..5)
+            .map(|_| ctx.get::<serde_json::Value>("Thing"))
+            .collect();
+        for request in requests {
+            assert!(block_on(request).is_ok());
+        }
+        assert_eq!(gauge.calls.load(Ordering::SeqCst), 5);
+        assert_eq!(gauge.peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_dropped_request_is_not_sent() {
+        let gauge = Arc::new(Gauge::default());
+        let ctx = AsyncClient::new(Client::new().with_transport(gauge.clone())).with_max_threads(1);
+        let first = ctx.get::<serde_json::Value>("Thing");
+        drop(ctx.get::<serde_json::Value>("Thing"));
+        let last = ctx.get::<serde_json::Value>("Thing");
+        assert!(block_on(first).is_ok());
+        assert!(block_on(last).is_ok());
+        assert_eq!(gauge.calls.load(Ordering::SeqCst), 2);
+    }
+}
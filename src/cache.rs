@@ -0,0 +1,304 @@
+//! Response caching for `GET` requests.
+//!
+//! A [`Client`](crate::Client) given a [`Cache`] with
+//! [`Client::with_cache`](crate::Client::with_cache) looks up every `GET`
+//! request there before going to the network, and stores successful
+//! responses for later calls. Responses served from the cache have
+//! [`Response::from_cache`] set.
+//!
+//! [`DiskCache`] keeps the responses as JSON files in a directory, so they
+//! survive between runs of a command line tool and allow working offline:
+//!
+//! ```no_run
+//! use klbfw::cache::DiskCache;
+//! use klbfw::Client;
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! # fn main() -> klbfw::Result<()> {
+//! let cache = DiskCache::new("/tmp/klbfw-cache", Duration::from_secs(3600));
+//! let ctx = Client::new().with_cache(Arc::new(cache));
+//! let _: serde_json::Value = ctx.get("Misc/Debug:serverTime")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Entries are keyed by [`cache_key`]: the method, API host, path,
+//! parameters and credentials, so a cache shared between clients, or a disk
+//! cache reused by another account, never serves one account's responses to
+//! another.
+
+use crate::apikey::ParamNames;
+use crate::error::Result;
+use crate::response::Response;
+use purecrypto::hash::sha256;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tempfile::NamedTempFile;
+
+/// Storage for cached responses
+///
+/// Implementations decide how long entries stay valid; a cache that fails
+/// to read or write should behave as a miss rather than fail the request.
+pub trait Cache: Send + Sync {
+    /// Get the response stored under `key`, if any and still valid
+    fn get(&self, key: &str) -> Option<Response>;
+
+    /// Store `response` under `key`
+    fn put(&self, key: &str, response: &Response);
+}
+
+/// Stable cache key of a request
+///
+/// A hex SHA-256 of the method, base URL, path, parameters and `identity`,
+/// which identifies the credentials the request is sent with (see
+/// [`Client::with_cache`](crate::Client::with_cache)). Object keys are sorted
+/// so the parameter order does not matter, and the API key signing
+/// parameters that change on every request (`_time`, `_nonce` and `_sign`,
+/// as named by `names`) are left out.
+pub fn cache_key(
+    method: &str,
+    base_url: &str,
+    path: &str,
+    params: &Value,
+    identity: &str,
+    names: &ParamNames,
+) -> String {
+    let volatile = names.volatile();
+    let params = match params {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(k, _)| !volatile.contains(&k.as_str()))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        ),
+        other => other.clone(),
+    };
+    let mut canonical = format!("{}\n{}\n{}\n{}\n", method, base_url, path, identity);
+    write_canonical(&params, &mut canonical);
+    sha256(canonical.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Serialize `value` as JSON with object keys sorted at every level.
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (k, v)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(k.clone()).to_string());
+                out.push(':');
+                write_canonical(v, out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+/// One cached response, as stored in its file.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    /// Unix time the response was stored
+    stored: u64,
+    response: Response,
+}
+
+/// Cache keeping responses as JSON files in a directory
+///
+/// Each entry is a `<key>.json` file. Entries older than the TTL are
+/// ignored and removed when next looked up; [`clear`](Self::clear) empties
+/// the cache. The directory is created on the first write.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl DiskCache {
+    /// Cache responses in `dir` for `ttl`
+    pub fn new(dir: impl AsRef<Path>, ttl: Duration) -> Self {
+        DiskCache {
+            dir: dir.as_ref().to_path_buf(),
+            ttl,
+        }
+    }
+
+    /// Directory holding the cache files
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// How long entries stay valid
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Remove every entry
+    ///
+    /// A missing directory is an empty cache. Files other than cache
+    /// entries are left alone.
+    pub fn clear(&self) -> Result<()> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+/// Current unix time in seconds.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl Cache for DiskCache {
+    fn get(&self, key: &str) -> Option<Response> {
+        let path = self.path(key);
+        let content = fs::read(&path).ok()?;
+        let entry: Entry = match serde_json::from_slice(&content) {
+            Ok(entry) => entry,
+            Err(_) => {
+                let _ = fs::remove_file(&path);
+                return None;
+            }
+        };
+        if now().saturating_sub(entry.stored) > self.ttl.as_secs() {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        Some(entry.response)
+    }
+
+    fn put(&self, key: &str, response: &Response) {
+        let entry = Entry {
+            stored: now(),
+            response: response.clone(),
+        };
+        let Ok(content) = serde_json::to_vec(&entry) else {
+            return;
+        };
+        // Write to a temporary file first so readers never see half an entry;
+        // it is unique to this writer, and removed if anything fails.
+        let path = self.path(key);
+        let _ = fs::create_dir_all(&self.dir)
+            .and_then(|_| NamedTempFile::new_in(&self.dir))
+            .and_then(|mut tmp| {
+                tmp.write_all(&content)?;
+                tmp.persist(&path).map_err(|e| e.error)
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cache_key() {
+        let base = "https://www.atonline.com/_rest/";
+        let names = ParamNames::default();
+        let key = |path, params: &Value| cache_key("GET", base, path, params, "", &names);
+        let first = key("Data/List", &json!({"a": 1, "b": {"y": 2, "x": 1}}));
+        assert_eq!(first.len(), 64);
+
+        // Order and signing parameters do not matter; everything else does.
+        let same = json!({"_nonce": "n", "b": {"x": 1, "y": 2}, "_time": 1, "a": 1});
+        assert_eq!(key("Data/List", &same), first);
+        assert_ne!(key("Data/List", &json!({"a": 2})), first);
+        assert_ne!(key("Data/Item", &same), first);
+        assert_ne!(
+            cache_key("GET", "http://localhost/", "Data/List", &same, "", &names),
+            first
+        );
+        assert_ne!(
+            cache_key("GET", base, "Data/List", &same, "key:k-1", &names),
+            first
+        );
+
+        // Renamed signing parameters are left out under their names.
+        let renamed = ParamNames {
+            time: "ts".to_string(),
+            ..ParamNames::default()
+        };
+        let params = json!({"a": 1, "b": {"y": 2, "x": 1}, "ts": 5});
+        assert_eq!(
+            cache_key("GET", base, "Data/List", &params, "", &renamed),
+            first
+        );
+        assert_ne!(key("Data/List", &params), first);
+    }
+
+    #[test]
+    fn test_disk_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::new(dir.path().join("cache"), Duration::from_secs(60));
+        assert!(cache.get("k").is_none());
+        assert!(cache.clear().is_ok());
+
+        cache.put("k", &Response::from_raw(json!({"id": 1})));
+        let cached = cache.get("k").unwrap();
+        assert_eq!(cached.data, Some(json!({"id": 1})));
+
+        cache.clear().unwrap();
+        assert!(cache.get("k").is_none());
+
+        // Writers of the same key do not share a temporary file.
+        std::thread::scope(|scope| {
+            for id in 0..8 {
+                let cache = &cache;
+                scope.spawn(move || cache.put("k", &Response::from_raw(json!({"id": id}))));
+            }
+        });
+        assert!(cache.get("k").is_some());
+        let files = fs::read_dir(dir.path().join("cache")).unwrap().count();
+        assert_eq!(files, 1, "temporary files left behind");
+        cache.clear().unwrap();
+
+        // Expired entries are dropped.
+        let cache = DiskCache::new(dir.path(), Duration::ZERO);
+        let entry = Entry {
+            stored: now() - 10,
+            response: Response::from_raw(json!(1)),
+        };
+        fs::write(cache.path("old"), serde_json::to_vec(&entry).unwrap()).unwrap();
+        assert!(cache.get("old").is_none());
+        assert!(!cache.path("old").exists());
+    }
+}
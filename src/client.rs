@@ -1,3 +1,59 @@
+use crate::error::{RestError, Result};
+use crate::retry::RetryPolicy;
+use std::time::Duration;
+
+/// Environment variable read by [`Config::from_env`] for the API base URL.
+pub const ENV_API_URL: &str = "KLBFW_API_URL";
+/// Default time limit for each REST request attempt.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+/// Default connection establishment timeout.
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Named bundle of timeout and retry settings for a kind of workload
+///
+/// [`Config::with_profile`] applies the timeouts;
+/// [`Client::with_profile`](crate::Client::with_profile) applies them along
+/// with the profile's [retry policy](Self::retry_policy):
+///
+/// | Profile       | timeout | connect | deadline | retries | first delay | max delay |
+/// |---------------|---------|---------|----------|---------|-------------|-----------|
+/// | `Interactive` | 15 s    | 5 s     | 30 s     | 1       | 200 ms      | 2 s       |
+/// | `Batch`       | 300 s   | 10 s    | none     | 5       | 1 s         | 60 s      |
+/// | `Upload`      | 3600 s  | 30 s    | none     | 3       | 1 s         | 30 s      |
+///
+/// The connect column is the connection timeout, and the deadline the
+/// [request deadline](Config::with_request_deadline).
+///
+/// The timeouts of `Batch` match the defaults, while a client retries
+/// nothing by default. These apply to REST calls; the data transfers of an
+/// upload have their own one-hour limit and retries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TimeoutProfile {
+    /// Requests a user is waiting on: fail fast
+    Interactive,
+    /// Background jobs: patient per request, no overall deadline
+    Batch,
+    /// Clients driving large uploads, whose completion calls can take long
+    Upload,
+}
+
+impl TimeoutProfile {
+    /// Retry policy matching the profile, on the default
+    /// [retryable statuses](RetryPolicy::with_retryable_statuses)
+    pub fn retry_policy(self) -> RetryPolicy {
+        let (max_retries, base_delay, max_delay) = match self {
+            TimeoutProfile::Interactive => (1, Duration::from_millis(200), Duration::from_secs(2)),
+            TimeoutProfile::Batch => (5, Duration::from_secs(1), Duration::from_secs(60)),
+            TimeoutProfile::Upload => (3, Duration::from_secs(1), Duration::from_secs(30)),
+        };
+        RetryPolicy::new()
+            .with_max_retries(max_retries)
+            .with_base_delay(base_delay)
+            .with_max_delay(max_delay)
+    }
+}
+
 /// Configuration for REST API client
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -7,6 +63,14 @@ pub struct Config {
     host: String,
     /// Enable debug logging
     debug: bool,
+    /// Total time budget for a logical request, including token renewal
+    request_deadline: Option<Duration>,
+    /// Time limit for each request attempt
+    timeout: Duration,
+    /// Time limit for establishing a connection
+    connect_timeout: Duration,
+    /// `User-Agent` header sent with requests, if any
+    user_agent: Option<String>,
 }
 
 impl Default for Config {
@@ -15,6 +79,10 @@ impl Default for Config {
             scheme: "https".to_string(),
             host: "www.atonline.com".to_string(),
             debug: false,
+            request_deadline: None,
+            timeout: DEFAULT_TIMEOUT,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            user_agent: None,
         }
     }
 }
@@ -26,6 +94,10 @@ impl Config {
             scheme,
             host,
             debug: false,
+            request_deadline: None,
+            timeout: DEFAULT_TIMEOUT,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            user_agent: None,
         }
     }
 
@@ -41,6 +113,50 @@ impl Config {
         }
     }
 
+    /// Create a configuration from a base URL such as
+    /// `https://staging.example.com:8443`
+    ///
+    /// The scheme must be `http` or `https`; a trailing `/` is allowed but
+    /// not a path, query or credentials. Invalid URLs are a
+    /// [`RestError::RequestBuild`].
+    pub fn from_url(url: &str) -> Result<Self> {
+        let invalid =
+            |why: &str| RestError::RequestBuild(format!("invalid API URL {:?}: {}", url, why));
+
+        let (scheme, rest) = url
+            .trim()
+            .split_once("://")
+            .ok_or_else(|| invalid("missing scheme"))?;
+        let scheme = scheme.to_ascii_lowercase();
+        if scheme != "http" && scheme != "https" {
+            return Err(invalid("scheme must be http or https"));
+        }
+        let host = rest.strip_suffix('/').unwrap_or(rest);
+        if host.is_empty() {
+            return Err(invalid("missing host"));
+        }
+        if host.contains(['/', '?', '#', '@']) || host.contains(char::is_whitespace) {
+            return Err(invalid("expected only a host and optional port"));
+        }
+
+        Ok(Config::new(scheme, host.to_string()))
+    }
+
+    /// Create a configuration from the `KLBFW_API_URL` environment variable
+    ///
+    /// The URL is read with [`from_url`](Self::from_url). Falls back to the
+    /// default configuration when the variable is unset or empty.
+    pub fn from_env() -> Result<Self> {
+        match std::env::var(ENV_API_URL) {
+            Ok(url) if !url.trim().is_empty() => Self::from_url(&url),
+            Ok(_) | Err(std::env::VarError::NotPresent) => Ok(Config::default()),
+            Err(e) => Err(RestError::RequestBuild(format!(
+                "environment variable {}: {}",
+                ENV_API_URL, e
+            ))),
+        }
+    }
+
     /// Set debug mode (builder style)
     pub fn with_debug(mut self, debug: bool) -> Self {
         self.debug = debug;
@@ -52,6 +168,80 @@ impl Config {
         self.debug = debug;
     }
 
+    /// Cap the total time spent on each request (builder style)
+    ///
+    /// The budget covers the whole logical request: the initial attempt and
+    /// any token renewal and retry. When it runs out the request fails with
+    /// [`RestError::DeadlineExceeded`].
+    /// Unset by default, leaving only the per-attempt timeouts; a budget
+    /// too large to add to the current time sets no deadline either.
+    pub fn with_request_deadline(mut self, budget: Duration) -> Self {
+        self.request_deadline = Some(budget);
+        self
+    }
+
+    /// Total time budget for a logical request, if set
+    pub fn request_deadline(&self) -> Option<Duration> {
+        self.request_deadline
+    }
+
+    /// Set the time limit for each request attempt (builder style)
+    ///
+    /// Defaults to 300 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Time limit for each request attempt
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Set the time limit for establishing a connection (builder style)
+    ///
+    /// Defaults to 10 seconds.
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Time limit for establishing a connection
+    pub fn connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+
+    /// Identify the application with a `User-Agent` header (builder style)
+    ///
+    /// Sent with REST requests and upload transfers. Unset by default,
+    /// leaving the HTTP library's default.
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// `User-Agent` header sent with requests, if set
+    pub fn user_agent(&self) -> Option<&str> {
+        self.user_agent.as_deref()
+    }
+
+    /// Apply the timeout settings of `profile` (builder style)
+    ///
+    /// Sets the timeout, connect timeout and request deadline; see
+    /// [`TimeoutProfile`] for the values. The profile's retry policy is
+    /// applied by [`Client::with_profile`](crate::Client::with_profile).
+    pub fn with_profile(mut self, profile: TimeoutProfile) -> Self {
+        let (timeout, connect_timeout, deadline) = match profile {
+            TimeoutProfile::Interactive => (15, 5, Some(30)),
+            TimeoutProfile::Batch => (300, 10, None),
+            TimeoutProfile::Upload => (3600, 30, None),
+        };
+        self.timeout = Duration::from_secs(timeout);
+        self.connect_timeout = Duration::from_secs(connect_timeout);
+        self.request_deadline = deadline.map(Duration::from_secs);
+        self
+    }
+
     /// URL scheme (http or https)
     pub fn scheme(&self) -> &str {
         &self.scheme
@@ -121,6 +311,72 @@ mod tests {
         assert_eq!(config.base_url(), "https://api.example.com");
     }
 
+    #[test]
+    fn test_profiles() {
+        let config = Config::default().with_profile(TimeoutProfile::Interactive);
+        assert_eq!(config.timeout(), Duration::from_secs(15));
+        assert_eq!(config.connect_timeout(), Duration::from_secs(5));
+        assert_eq!(config.request_deadline(), Some(Duration::from_secs(30)));
+
+        let config = config.with_profile(TimeoutProfile::Batch);
+        let default = Config::default();
+        assert_eq!(config.timeout(), default.timeout());
+        assert_eq!(config.connect_timeout(), default.connect_timeout());
+        assert_eq!(config.request_deadline(), None);
+
+        let config = config.with_profile(TimeoutProfile::Upload);
+        assert_eq!(config.timeout(), Duration::from_secs(3600));
+
+        let policy = TimeoutProfile::Interactive.retry_policy();
+        assert_eq!(policy.max_retries(), 1);
+        assert_eq!(policy.max_delay(), Duration::from_secs(2));
+        let policy = TimeoutProfile::Batch.retry_policy();
+        assert_eq!(policy.max_retries(), 5);
+        assert_eq!(policy.base_delay(), Duration::from_secs(1));
+        assert_eq!(policy.max_delay(), Duration::from_secs(60));
+        assert_eq!(TimeoutProfile::Upload.retry_policy().max_retries(), 3);
+    }
+
+    #[test]
+    fn test_from_url() {
+        let config = Config::from_url("https://staging.example.com:8443/").unwrap();
+        assert_eq!(config.base_url(), "https://staging.example.com:8443");
+        let config = Config::from_url("HTTP://localhost").unwrap();
+        assert_eq!(config.base_url(), "http://localhost");
+
+        for bad in [
+            "staging.example.com",
+            "ftp://example.com",
+            "https://",
+            "https://example.com/api",
+            "https://user@example.com",
+            "https://example.com?x=1",
+        ] {
+            assert!(
+                matches!(Config::from_url(bad), Err(RestError::RequestBuild(_))),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_env() {
+        std::env::remove_var(ENV_API_URL);
+        assert_eq!(
+            Config::from_env().unwrap().base_url(),
+            "https://www.atonline.com"
+        );
+        std::env::set_var(ENV_API_URL, "http://localhost:8080");
+        assert_eq!(
+            Config::from_env().unwrap().base_url(),
+            "http://localhost:8080"
+        );
+        std::env::set_var(ENV_API_URL, "localhost");
+        assert!(Config::from_env().is_err());
+        std::env::remove_var(ENV_API_URL);
+    }
+
     #[test]
     fn test_base_url_idna() {
         let config = Config::new("https".to_string(), "bücher.example".to_string());
@@ -0,0 +1,55 @@
+//! Typed wrappers for the `Misc/Debug` test endpoints.
+//!
+//! These endpoints exist for testing the client against a live server. The
+//! wrappers double as a reference for writing typed endpoint functions on top
+//! of [`Client::apply`]: bake the path and method in, take typed input, and
+//! return typed output.
+
+use crate::error::Result;
+use crate::rest::Client;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Response of `Misc/Debug:argument`.
+#[derive(Debug, Deserialize)]
+struct Argument {
+    input: String,
+}
+
+/// Response of `Misc/Debug:argString`.
+#[derive(Debug, Deserialize)]
+struct ArgString {
+    input_string: String,
+}
+
+/// Call `Misc/Debug:fixedArray`, which returns a fixed associative array.
+pub fn fixed_array(ctx: &Client) -> Result<HashMap<String, Value>> {
+    ctx.apply("Misc/Debug:fixedArray", "GET", serde_json::json!({}))
+}
+
+/// Call `Misc/Debug:fixedString`, which returns a fixed string.
+pub fn fixed_string(ctx: &Client) -> Result<String> {
+    ctx.apply("Misc/Debug:fixedString", "GET", serde_json::json!({}))
+}
+
+/// Call `Misc/Debug:argument`, which echoes its `input` parameter back.
+pub fn echo_argument(ctx: &Client, input: &str) -> Result<String> {
+    let res: Argument = ctx.apply(
+        "Misc/Debug:argument",
+        "GET",
+        serde_json::json!({ "input": input }),
+    )?;
+    Ok(res.input)
+}
+
+/// Call `Misc/Debug:argString`, which echoes its `input_string` parameter
+/// back.
+pub fn echo_string(ctx: &Client, input: &str) -> Result<String> {
+    let res: ArgString = ctx.apply(
+        "Misc/Debug:argString",
+        "GET",
+        serde_json::json!({ "input_string": input }),
+    )?;
+    Ok(res.input_string)
+}
@@ -0,0 +1,264 @@
+//! Blob downloads.
+//!
+//! The read-side counterpart of [`upload`](crate::upload()): looks up a blob
+//! through the REST API, then streams its content from the returned URL to a
+//! writer, optionally resuming a partial download.
+
+use crate::error::{RestError, Result};
+use crate::rest::Client;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::time::Duration;
+
+/// Overall request timeout for downloads (1 hour).
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(3600);
+/// Connection establishment timeout.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Size of the copy buffer.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Download progress callback, called with the bytes held by the writer so
+/// far and the total size when known
+pub type DownloadProgressFn = Box<dyn Fn(u64, Option<u64>) + Send + Sync>;
+
+/// Blob details returned by the `Blob/{id}` endpoint.
+#[derive(Debug, Deserialize)]
+struct BlobInfo {
+    /// Download URL of the content
+    #[serde(rename = "Url")]
+    url: String,
+    /// Content size in bytes
+    #[serde(rename = "Size", default, deserialize_with = "deserialize_size")]
+    size: Option<u64>,
+}
+
+/// Accept a size sent as a number or a numeric string.
+fn deserialize_size<'de, D>(deserializer: D) -> std::result::Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    })
+}
+
+/// Download a blob's content to `writer`
+///
+/// # Arguments
+/// * `ctx` - REST context for authentication
+/// * `blob_id` - Blob identifier (`blob-...`)
+/// * `writer` - Destination; written from the start, or appended to when
+///   resuming
+/// * `progress` - Optional progress callback
+/// * `resume` - Continue after the data already in `writer` using an HTTP
+///   `Range` request
+///
+/// When resuming, servers that ignore `Range` send the whole content, which
+/// then overwrites `writer` from the start. Without `resume`, `writer` should
+/// be empty: it is written from the start but not truncated.
+///
+/// Returns the size of the downloaded content.
+pub fn download_blob<W: Write + Seek>(
+    ctx: &Client,
+    blob_id: &str,
+    writer: W,
+    progress: Option<DownloadProgressFn>,
+    resume: bool,
+) -> Result<u64> {
+    let info: BlobInfo = ctx.apply(&format!("Blob/{}", blob_id), "GET", serde_json::json!({}))?;
+    fetch_to(&info.url, info.size, writer, progress.as_deref(), resume)
+}
+
+/// Stream `url` to `writer`, resuming after the existing data if asked.
+fn fetch_to<W: Write + Seek>(
+    url: &str,
+    size: Option<u64>,
+    mut writer: W,
+    progress: Option<&(dyn Fn(u64, Option<u64>) + Send + Sync)>,
+    resume: bool,
+) -> Result<u64> {
+    let report = |done: u64| {
+        if let Some(progress) = progress {
+            progress(done, size);
+        }
+    };
+
+    let offset = if resume {
+        writer.seek(SeekFrom::End(0))?
+    } else {
+        writer.seek(SeekFrom::Start(0))?
+    };
+    match size {
+        Some(size) if offset == size && offset > 0 => {
+            report(offset);
+            return Ok(offset);
+        }
+        Some(size) if offset > size => {
+            return Err(RestError::Other(format!(
+                "cannot resume: writer holds {} bytes, more than the blob's {}",
+                offset, size
+            )));
+        }
+        _ => {}
+    }
+
+    let mut request = rsurl::Request::new("GET", url)?
+        .follow_redirects(true)
+        .max_time(DOWNLOAD_TIMEOUT)
+        .connect_timeout(CONNECT_TIMEOUT);
+    if offset > 0 {
+        request = request.header("Range", &format!("bytes={}-", offset));
+    }
+    let mut body = request.send_reader()?;
+
+    let mut done = match body.status() {
+        206 => {
+            // Make sure the server continues exactly where we stopped.
+            let expected = format!("bytes {}-", offset);
+            if !body
+                .header("Content-Range")
+                .is_some_and(|range| range.starts_with(&expected))
+            {
+                return Err(RestError::Other(
+                    "server resumed the download at the wrong offset".to_string(),
+                ));
+            }
+            offset
+        }
+        200 => {
+            // Range unsupported (or not requested): take the whole content.
+            writer.seek(SeekFrom::Start(0))?;
+            0
+        }
+        status => {
+            return Err(RestError::http(
+                status,
+                format!("Blob download failed with status {}", status),
+                None,
+            ))
+        }
+    };
+    report(done);
+
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    loop {
+        let n = body.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..n])?;
+        done += n as u64;
+        report(done);
+    }
+    writer.flush()?;
+
+    match size {
+        Some(size) if size != done => Err(RestError::Other(format!(
+            "download incomplete: got {} of {} bytes",
+            done, size
+        ))),
+        _ => Ok(done),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Cursor};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    const CONTENT: &[u8] = b"0123456789";
+
+    /// Serve `CONTENT` once on a local port, honoring `Range` when
+    /// `ranges` is set. Returns the URL and the Range header received.
+    fn serve_once(ranges: bool) -> (String, std::thread::JoinHandle<Option<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/blob", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut range = None;
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some(v) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+                    range = Some(v.trim().trim_end_matches('-').to_string());
+                }
+            }
+
+            let start = match (&range, ranges) {
+                (Some(start), true) => start.parse().unwrap(),
+                _ => 0,
+            };
+            let body = &CONTENT[start..];
+            let head = if start > 0 {
+                format!(
+                    "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\n",
+                    start,
+                    CONTENT.len() - 1,
+                    CONTENT.len()
+                )
+            } else {
+                "HTTP/1.1 200 OK\r\n".to_string()
+            };
+            let response = format!(
+                "{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                head,
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+            stream.write_all(body).unwrap();
+            range
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_fetch_full() {
+        let (url, server) = serve_once(true);
+        let mut out = Cursor::new(Vec::new());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let progress = move |done, total| log.lock().unwrap().push((done, total));
+
+        let size = fetch_to(&url, Some(10), &mut out, Some(&progress), false).unwrap();
+        assert_eq!(size, 10);
+        assert_eq!(out.into_inner(), CONTENT);
+        assert_eq!(server.join().unwrap(), None);
+        assert_eq!(seen.lock().unwrap().last(), Some(&(10, Some(10))));
+    }
+
+    #[test]
+    fn test_fetch_resume() {
+        let (url, server) = serve_once(true);
+        let mut out = Cursor::new(b"01234".to_vec());
+        let size = fetch_to(&url, Some(10), &mut out, None, true).unwrap();
+        assert_eq!(size, 10);
+        assert_eq!(out.into_inner(), CONTENT);
+        assert_eq!(server.join().unwrap().as_deref(), Some("5"));
+    }
+
+    #[test]
+    fn test_fetch_resume_without_range_support() {
+        let (url, server) = serve_once(false);
+        let mut out = Cursor::new(b"01234".to_vec());
+        fetch_to(&url, Some(10), &mut out, None, true).unwrap();
+        assert_eq!(out.into_inner(), CONTENT);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_fetch_already_complete() {
+        // No request is made when the writer already holds the whole blob.
+        let mut out = Cursor::new(CONTENT.to_vec());
+        let size = fetch_to("http://127.0.0.1:1/", Some(10), &mut out, None, true).unwrap();
+        assert_eq!(size, 10);
+    }
+}
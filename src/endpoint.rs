@@ -0,0 +1,259 @@
+//! Endpoint introspection.
+//!
+//! Endpoints answer `OPTIONS` requests with a description of themselves: the
+//! methods they accept and their parameters. [`Client::describe`] fetches and
+//! parses it, so generic tools (UIs, CLIs) can adapt to endpoint definitions
+//! at runtime.
+//!
+//! The [`endpoint!`](crate::endpoint!) macro covers the opposite direction:
+//! declaring typed functions for endpoints known at compile time.
+//!
+//! [`Client::describe`]: crate::Client::describe
+
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+
+/// Description of an endpoint, as returned by [`Client::describe`]
+///
+/// Fields the server sends beyond the known ones are kept in `extra`.
+///
+/// [`Client::describe`]: crate::Client::describe
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct EndpointInfo {
+    /// HTTP methods the endpoint accepts
+    #[serde(default, alias = "allowed_methods")]
+    pub methods: Vec<String>,
+
+    /// Accepted parameters
+    #[serde(default, alias = "arguments", deserialize_with = "deserialize_params")]
+    pub params: Vec<ParamInfo>,
+
+    /// Any other fields of the description
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl EndpointInfo {
+    /// Look up a parameter by name
+    pub fn param(&self, name: &str) -> Option<&ParamInfo> {
+        self.params.iter().find(|p| p.name == name)
+    }
+
+    /// The parameters that must be provided
+    pub fn required_params(&self) -> impl Iterator<Item = &ParamInfo> {
+        self.params.iter().filter(|p| p.required)
+    }
+}
+
+/// Description of one endpoint parameter
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct ParamInfo {
+    /// Parameter name
+    #[serde(default)]
+    pub name: String,
+
+    /// Type name as reported by the server (e.g. `string`, `int`)
+    #[serde(default, rename = "type")]
+    pub kind: Option<String>,
+
+    /// Whether the parameter must be provided
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub required: bool,
+
+    /// Human-readable description
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// Any other fields of the description (default value, enum values, ...)
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Accept parameters as a list, or as an object keyed by parameter name.
+fn deserialize_params<'de, D>(deserializer: D) -> Result<Vec<ParamInfo>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    match Value::deserialize(deserializer)? {
+        Value::Null => Ok(Vec::new()),
+        Value::Array(items) => items
+            .into_iter()
+            .map(|v| serde_json::from_value(v).map_err(D::Error::custom))
+            .collect(),
+        Value::Object(map) => map
+            .into_iter()
+            .map(|(name, v)| {
+                let mut param: ParamInfo = serde_json::from_value(v).map_err(D::Error::custom)?;
+                if param.name.is_empty() {
+                    param.name = name;
+                }
+                Ok(param)
+            })
+            .collect(),
+        other => Err(D::Error::custom(format!(
+            "expected a list or map of parameters, got {}",
+            other
+        ))),
+    }
+}
+
+/// Accept PHP-style flags: booleans, `0`/`1`, or `"0"`/`"1"`.
+fn deserialize_flag<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Bool(b) => b,
+        Value::Number(n) => n.as_i64() != Some(0),
+        Value::String(s) => !matches!(s.as_str(), "" | "0" | "false"),
+        _ => false,
+    })
+}
+
+/// Declare a typed function for an endpoint
+///
+/// Bakes the path and HTTP method into a function over
+/// [`Client::apply`](crate::Client::apply), so call sites cannot pass the
+/// wrong method or misspell the path. The method is one of `GET`, `HEAD`,
+/// `OPTIONS`, `POST`, `PUT`, `PATCH` or `DELETE`; anything else fails to
+/// compile. With `input`, the function takes the request parameters by
+/// reference; without, it sends none.
+///
+/// ```no_run
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize)]
+/// struct UserGetReq {
+///     id: String,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// klbfw::endpoint! {
+///     /// Fetch a user
+///     pub fn users_get("User:get", GET, input = UserGetReq, output = User);
+///
+///     /// Fetch a fixed string
+///     pub fn fixed_string("Misc/Debug:fixedString", GET, output = String);
+/// }
+///
+/// # fn main() -> klbfw::Result<()> {
+/// let ctx = klbfw::Client::new();
+/// let user = users_get(&ctx, &UserGetReq { id: "usr-123".to_string() })?;
+/// println!("{} / {}", user.name, fixed_string(&ctx)?);
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! endpoint {
+    () => {};
+    (
+        $(#[$attr:meta])*
+        $vis:vis fn $name:ident($path:literal, $method:ident, input = $input:ty, output = $output:ty $(,)?);
+        $($rest:tt)*
+    ) => {
+        $(#[$attr])*
+        $vis fn $name(ctx: &$crate::Client, input: &$input) -> $crate::Result<$output> {
+            ctx.apply($path, $crate::endpoint!(@method $method), input)
+        }
+        $crate::endpoint!($($rest)*);
+    };
+    (
+        $(#[$attr:meta])*
+        $vis:vis fn $name:ident($path:literal, $method:ident, output = $output:ty $(,)?);
+        $($rest:tt)*
+    ) => {
+        $(#[$attr])*
+        $vis fn $name(ctx: &$crate::Client) -> $crate::Result<$output> {
+            ctx.apply($path, $crate::endpoint!(@method $method), ())
+        }
+        $crate::endpoint!($($rest)*);
+    };
+    (@method GET) => { "GET" };
+    (@method HEAD) => { "HEAD" };
+    (@method OPTIONS) => { "OPTIONS" };
+    (@method POST) => { "POST" };
+    (@method PUT) => { "PUT" };
+    (@method PATCH) => { "PATCH" };
+    (@method DELETE) => { "DELETE" };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::testing::Capture;
+    use crate::Client;
+    use serde_json::json;
+
+    #[test]
+    fn test_endpoint_info_list() {
+        let info: EndpointInfo = serde_json::from_value(json!({
+            "methods": ["GET", "POST"],
+            "params": [
+                {"name": "input", "type": "string", "required": true},
+                {"name": "limit", "type": "int", "default": 10}
+            ],
+            "description": "Echo"
+        }))
+        .unwrap();
+
+        assert_eq!(info.methods, ["GET", "POST"]);
+        assert_eq!(info.param("input").unwrap().kind.as_deref(), Some("string"));
+        assert_eq!(info.param("limit").unwrap().extra["default"], 10);
+        let required: Vec<&str> = info.required_params().map(|p| p.name.as_str()).collect();
+        assert_eq!(required, ["input"]);
+        assert_eq!(info.extra["description"], "Echo");
+    }
+
+    #[test]
+    fn test_endpoint_info_map() {
+        let info: EndpointInfo = serde_json::from_value(json!({
+            "arguments": {
+                "input_string": {"type": "string", "required": "1"},
+                "flag": {"type": "bool", "required": 0}
+            }
+        }))
+        .unwrap();
+
+        assert!(info.methods.is_empty());
+        assert!(info.param("input_string").unwrap().required);
+        assert!(!info.param("flag").unwrap().required);
+    }
+
+    #[derive(serde::Serialize)]
+    struct EchoReq {
+        input: String,
+    }
+
+    crate::endpoint! {
+        fn echo("Misc/Debug:argString", POST, input = EchoReq, output = String);
+        fn fixed("Misc/Debug:fixedString", GET, output = String);
+    }
+
+    #[test]
+    fn test_endpoint_macro() {
+        let capture =
+            std::sync::Arc::new(Capture::answering(r#"{"result":"success","data":"ok"}"#));
+        let ctx = Client::new().with_transport(capture.clone());
+
+        let req = EchoReq {
+            input: "hi".to_string(),
+        };
+        assert_eq!(echo(&ctx, &req).unwrap(), "ok");
+        assert_eq!(fixed(&ctx).unwrap(), "ok");
+
+        let requests = capture.0.lock().unwrap();
+        assert_eq!(requests[0].method, "POST");
+        assert!(requests[0].url.ends_with("/Misc/Debug:argString"));
+        assert_eq!(requests[0].body, br#"{"input":"hi"}"#);
+        assert_eq!(requests[1].method, "GET");
+        assert!(requests[1].url.contains("/Misc/Debug:fixedString?"));
+    }
+}
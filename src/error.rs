@@ -1,6 +1,31 @@
 use crate::response::Response;
+use crate::trace::{REDACTED, SECRET_HEADERS, SECRET_PARAMS};
+use serde_json::{Map, Value};
+use std::sync::Mutex;
+use std::time::Duration;
 use thiserror::Error;
 
+/// Text of maintenance pages, matched case-insensitively.
+const MAINTENANCE_MARKER: &str = "maintenance";
+/// Longest line [`RestError::report`] prints, in characters.
+const REPORT_LINE_MAX: usize = 500;
+
+/// Authorization schemes whose credentials follow them, masked by
+/// [`RestError::report`].
+const AUTH_SCHEMES: [&str; 2] = ["Bearer", "Basic"];
+
+/// Renamed signature parameters of the API keys given to clients, masked by
+/// [`RestError::report`] along with the [`SECRET_PARAMS`].
+static REPORT_SIGN_PARAMS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Mask the signature parameter `name` in error reports from now on.
+pub(crate) fn mask_in_reports(name: &str) {
+    let mut params = REPORT_SIGN_PARAMS.lock().unwrap();
+    if !SECRET_PARAMS.contains(&name) && !params.iter().any(|param| param == name) {
+        params.push(name.to_string());
+    }
+}
+
 /// Main error type for REST API operations
 #[derive(Debug, Error)]
 #[non_exhaustive]
@@ -14,6 +39,18 @@ pub enum RestError {
         response: Box<Response>,
     },
 
+    /// Redirect returned by a REST API endpoint
+    ///
+    /// Login redirects are reported as [`LoginRequired`](Self::LoginRequired)
+    /// instead. The response may carry `data`, such as a partial result; see
+    /// [`redirect_params`](RestError::redirect_params) to follow it.
+    #[error("REST API redirect to {}", .url.as_deref().unwrap_or("an unknown location"))]
+    Redirect {
+        url: Option<String>,
+        code: Option<i32>,
+        response: Box<Response>,
+    },
+
     /// HTTP transport error
     #[error("HTTP error {status}: {body}")]
     Http {
@@ -34,6 +71,26 @@ pub enum RestError {
     #[error("no refresh token available and access token has expired")]
     NoRefreshToken,
 
+    /// The access token expired and the renewal request could not reach the
+    /// server
+    ///
+    /// `expired` is the server's original token-expired error and `source`
+    /// the transport failure of the renewal, typically a lost network
+    /// connection. The error is retryable once the server is reachable again.
+    #[error("access token expired and could not be renewed, server unreachable: {source}")]
+    RenewalUnreachable {
+        expired: Box<RestError>,
+        source: Box<RestError>,
+    },
+
+    /// Response carried no `data` where some was expected
+    #[error("response has no data")]
+    EmptyData,
+
+    /// The request's overall deadline passed before it completed
+    #[error("request deadline exceeded")]
+    DeadlineExceeded,
+
     /// Request building error
     #[error("failed to build request: {0}")]
     RequestBuild(String),
@@ -57,11 +114,120 @@ pub enum RestError {
     /// Other errors
     #[error("{0}")]
     Other(String),
+
+    /// Response data that does not match the client's JSON Schema
+    ///
+    /// Only produced by clients configured with `with_response_validator`
+    /// (`schema-validation` feature). Each entry names the location in the
+    /// data and what is wrong there, e.g. `data/name: 7 is not of type
+    /// "string"`.
+    #[error("response does not match the schema: {}", .errors.join("; "))]
+    SchemaViolation { errors: Vec<String> },
+
+    /// The server's hash of an upload differs from the uploaded content's
+    ///
+    /// Only checked with
+    /// [`UploadOptions::with_verify_checksum`](crate::UploadOptions::with_verify_checksum).
+    /// Both are hex-encoded SHA-256 digests.
+    #[error("upload checksum mismatch: sent {expected}, server has {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+
+    /// An upload stopped through its cancel flag
+    ///
+    /// See [`UploadOptions::with_cancel_flag`](crate::UploadOptions::with_cancel_flag).
+    #[error("upload cancelled")]
+    Cancelled,
+
+    /// A job waited on with [`Client::wait_for_job`](crate::Client::wait_for_job)
+    /// stopped without completing
+    ///
+    /// `status` is the job's final status (e.g. `failed` or `cancelled`),
+    /// `message` the reason given by the server if any, and `response` the
+    /// status response reporting it.
+    #[error("job {id} {status}{}", .message.as_ref().map(|m| format!(": {}", m)).unwrap_or_default())]
+    JobFailed {
+        id: String,
+        status: String,
+        message: Option<String>,
+        response: Box<Response>,
+    },
+
+    /// A job waited on with [`Client::wait_for_job`](crate::Client::wait_for_job)
+    /// had not finished after `timeout`
+    ///
+    /// The job itself may still complete later.
+    #[error("job {id} not finished after {}s", .timeout.as_secs())]
+    JobTimeout { id: String, timeout: Duration },
+
+    /// A multipart upload part that failed for good
+    ///
+    /// Transient failures are retried first (see
+    /// [`UploadOptions::with_max_part_retries`](crate::UploadOptions::with_max_part_retries));
+    /// `attempts` counts every try, the first included. Like
+    /// [`Correlated`](Self::Correlated), the predicates and status accessors
+    /// look through this wrapper.
+    #[error("upload of part {part} failed after {attempts} attempt(s): {source}")]
+    UploadPart {
+        part: i32,
+        attempts: u32,
+        source: Box<RestError>,
+    },
+
+    /// A request rejected with status 429 and a `Retry-After` delay
+    ///
+    /// `retry_after` is how long the server asks to wait before sending
+    /// requests again, and `source` the [`Api`](Self::Api) or
+    /// [`Http`](Self::Http) error of the response. 429 responses without a
+    /// usable `Retry-After` only give that error. The predicates and status
+    /// accessors look through this wrapper; [`retry_after`](RestError::retry_after)
+    /// finds the delay through a correlation id wrapper.
+    #[error("rate limited, retry after {}s: {source}", .retry_after.as_secs())]
+    RateLimited {
+        retry_after: Duration,
+        source: Box<RestError>,
+    },
+
+    /// An error raised by a request sent with a correlation id
+    ///
+    /// Only produced by clients configured with
+    /// [`Client::with_correlation_id`](crate::Client::with_correlation_id).
+    /// The predicates and status accessors look through this wrapper; use
+    /// [`inner`](RestError::inner) to match on the underlying error.
+    #[error("{source} (correlation id: {correlation_id})")]
+    Correlated {
+        correlation_id: String,
+        source: Box<RestError>,
+    },
+}
+
+/// Broad cause of an authentication failure, see [`RestError::auth_error_kind`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AuthErrorKind {
+    /// No credentials were provided where they are required
+    Missing,
+    /// The credentials were rejected
+    Invalid,
+    /// The credentials expired and could not be renewed
+    Expired,
+    /// The client is set up in a way that prevents authenticating (e.g. a
+    /// token without a client ID)
+    Misconfigured,
 }
 
 impl RestError {
     /// Create a new API error from a Response
+    ///
+    /// Redirect responses give a [`Redirect`](Self::Redirect) error.
     pub fn from_response(response: Response) -> Self {
+        if response.result == "redirect" {
+            return RestError::Redirect {
+                url: response.redirect_url.clone(),
+                code: response.redirect_code,
+                response: Box::new(response),
+            };
+        }
+
         let message = response
             .error
             .clone()
@@ -90,10 +256,81 @@ impl RestError {
         }
     }
 
+    /// The correlation id of the request that raised this error, if any
+    pub fn correlation_id(&self) -> Option<&str> {
+        match self {
+            RestError::Correlated { correlation_id, .. } => Some(correlation_id),
+            _ => None,
+        }
+    }
+
+    /// The underlying error, with any correlation id, upload part or rate
+    /// limit wrapper removed
+    pub fn inner(&self) -> &RestError {
+        match self {
+            RestError::Correlated { source, .. }
+            | RestError::UploadPart { source, .. }
+            | RestError::RateLimited { source, .. } => source.inner(),
+            other => other,
+        }
+    }
+
+    /// How long the server asked to wait before retrying, for
+    /// [`RateLimited`](Self::RateLimited) errors
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            RestError::RateLimited { retry_after, .. } => Some(*retry_after),
+            RestError::Correlated { source, .. } | RestError::UploadPart { source, .. } => {
+                source.retry_after()
+            }
+            _ => None,
+        }
+    }
+
+    /// Classify an authentication failure, or `None` for other errors
+    ///
+    /// Covers [`LoginRequired`](Self::LoginRequired) (missing),
+    /// [`NoRefreshToken`](Self::NoRefreshToken) and
+    /// [`RenewalUnreachable`](Self::RenewalUnreachable) (expired),
+    /// [`NoClientId`](Self::NoClientId) (misconfigured), and API or HTTP
+    /// errors with status 401 or a rejected token. Permission errors (403) are
+    /// not authentication failures: the caller is known but not allowed.
+    pub fn auth_error_kind(&self) -> Option<AuthErrorKind> {
+        match self.inner() {
+            RestError::LoginRequired => Some(AuthErrorKind::Missing),
+            RestError::NoRefreshToken | RestError::RenewalUnreachable { .. } => {
+                Some(AuthErrorKind::Expired)
+            }
+            RestError::NoClientId => Some(AuthErrorKind::Misconfigured),
+            RestError::Api { code, response, .. } => {
+                if response.extra.as_deref() == Some("token_expired") {
+                    Some(AuthErrorKind::Expired)
+                } else if *code == Some(401)
+                    || response.token.as_deref() == Some("invalid_request_token")
+                {
+                    Some(AuthErrorKind::Invalid)
+                } else {
+                    None
+                }
+            }
+            RestError::Http { status: 401, .. } => Some(AuthErrorKind::Invalid),
+            _ => None,
+        }
+    }
+
+    /// Check if this error is an authentication failure of any kind
+    ///
+    /// Lets middleware catch every auth failure in one place, e.g. to start a
+    /// re-login flow. See [`auth_error_kind`](Self::auth_error_kind) for the
+    /// cases covered.
+    pub fn is_auth_error(&self) -> bool {
+        self.auth_error_kind().is_some()
+    }
+
     /// Check if this error is a permission denied error (403)
     pub fn is_permission_denied(&self) -> bool {
         matches!(
-            self,
+            self.inner(),
             RestError::Api {
                 code: Some(403),
                 ..
@@ -104,7 +341,7 @@ impl RestError {
     /// Check if this error is a not found error (404)
     pub fn is_not_found(&self) -> bool {
         matches!(
-            self,
+            self.inner(),
             RestError::Api {
                 code: Some(404),
                 ..
@@ -112,16 +349,291 @@ impl RestError {
         )
     }
 
+    /// Map this error to an HTTP status code, for services that re-expose
+    /// errors over their own HTTP API.
+    ///
+    /// - `Api` and `Http` keep their status when it is a 4xx/5xx code
+    /// - `LoginRequired`, `NoClientId` and `NoRefreshToken` map to 401
+    /// - `RequestBuild` maps to 400
+    /// - `DeadlineExceeded` maps to 504
+    /// - `Transport` maps to 504 for timeouts and 502 otherwise
+    /// - `RenewalUnreachable` maps like its transport failure
+    /// - everything else maps to 500
+    pub fn http_status(&self) -> u16 {
+        match self.inner() {
+            RestError::Api { code, .. } => code
+                .and_then(|c| u16::try_from(c).ok())
+                .filter(|c| (400..600).contains(c))
+                .unwrap_or(500),
+            RestError::Http { status, .. } if (400..600).contains(status) => *status,
+            RestError::LoginRequired | RestError::NoClientId | RestError::NoRefreshToken => 401,
+            RestError::RequestBuild(_) => 400,
+            RestError::DeadlineExceeded => 504,
+            RestError::Transport(rsurl::Error::Io(e))
+                if e.kind() == std::io::ErrorKind::TimedOut =>
+            {
+                504
+            }
+            RestError::Transport(_) => 502,
+            RestError::RenewalUnreachable { source, .. } => source.http_status(),
+            _ => 500,
+        }
+    }
+
+    /// Check if this error is transient, so the request may succeed if sent
+    /// again
+    ///
+    /// True for connection failures and timeouts of the transport (including
+    /// during token renewal), and for
+    /// `Api`, `Http` or transport status errors with status 429 or 5xx. False
+    /// for everything else, including 4xx statuses, (de)serialization errors
+    /// and [`DeadlineExceeded`](Self::DeadlineExceeded), whose time budget is
+    /// already spent. Only retry requests that are safe to repeat.
+    pub fn is_retryable(&self) -> bool {
+        fn retryable_status(status: i64) -> bool {
+            status == 429 || (500..600).contains(&status)
+        }
+
+        match self.inner() {
+            RestError::Api { code, .. } => code.is_some_and(|c| retryable_status(c.into())),
+            RestError::Http { status, .. } => retryable_status((*status).into()),
+            RestError::Transport(rsurl::Error::Status { code, .. }) => {
+                retryable_status((*code).into())
+            }
+            RestError::Transport(rsurl::Error::UnexpectedEof) => true,
+            RestError::RenewalUnreachable { .. } => true,
+            RestError::Transport(rsurl::Error::Io(e)) => matches!(
+                e.kind(),
+                std::io::ErrorKind::ConnectionRefused
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::NotConnected
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::UnexpectedEof
+                    | std::io::ErrorKind::Interrupted
+            ),
+            _ => false,
+        }
+    }
+
+    /// Check if this error comes from the server being in maintenance
+    ///
+    /// True for status 503, and for other 5xx errors whose body or message
+    /// mentions maintenance (maintenance pages are usually HTML, not the JSON
+    /// envelope). Such errors are [retryable](Self::is_retryable), but only
+    /// after a longer wait than other transient failures; a client can also
+    /// tell its user the service is under maintenance.
+    pub fn is_maintenance(&self) -> bool {
+        let marked = |text: &str| text.to_ascii_lowercase().contains(MAINTENANCE_MARKER);
+        match self.inner() {
+            RestError::Api { code, message, .. } => {
+                *code == Some(503)
+                    || (code.is_some_and(|c| (500..600).contains(&c)) && marked(message))
+            }
+            RestError::Http { status, body, .. } => {
+                *status == 503 || ((500..600).contains(status) && marked(body))
+            }
+            RestError::Transport(rsurl::Error::Status { code, .. }) => *code == 503,
+            _ => false,
+        }
+    }
+
+    /// Parameters to continue a [`Redirect`](Self::Redirect), or `None` for
+    /// other errors
+    ///
+    /// These are the query parameters of the redirect URL, overridden by the
+    /// fields of the response's `data` when it is an object (e.g. a
+    /// continuation token). Send them to the endpoint the redirect points to.
+    pub fn redirect_params(&self) -> Option<Map<String, Value>> {
+        let RestError::Redirect { url, response, .. } = self.inner() else {
+            return None;
+        };
+
+        let query = url
+            .as_deref()
+            .and_then(|url| url.split_once('?'))
+            .map(|(_, query)| query.split('#').next().unwrap_or_default());
+        let mut params: Map<String, Value> =
+            form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+                .map(|(k, v)| (k.into_owned(), Value::String(v.into_owned())))
+                .collect();
+        if let Some(Value::Object(data)) = &response.data {
+            params.extend(data.clone());
+        }
+        Some(params)
+    }
+
+    /// The server response behind an `Api` or `Redirect` error.
+    fn response(&self) -> Option<&Response> {
+        match self.inner() {
+            RestError::Api { response, .. } | RestError::Redirect { response, .. } => {
+                Some(response)
+            }
+            _ => None,
+        }
+    }
+
+    /// The `X-Request-Id` of the response that raised this error, if any
+    pub fn request_id(&self) -> Option<&str> {
+        match self.inner() {
+            RestError::Api { request_id, .. } => request_id.as_deref(),
+            _ => self.response()?.request_id.as_deref(),
+        }
+    }
+
+    /// The server's error token (the response's `token` field, e.g.
+    /// `invalid_request_token`), if any
+    pub fn error_token(&self) -> Option<&str> {
+        self.response()?.token.as_deref()
+    }
+
+    /// The server-side exception class (e.g. `Exception\Login`), if any
+    pub fn exception(&self) -> Option<&str> {
+        self.response()?.exception.as_deref()
+    }
+
+    /// Name of the variant, looking through wrappers.
+    fn kind(&self) -> &'static str {
+        match self.inner() {
+            RestError::Api { .. } => "Api",
+            RestError::Redirect { .. } => "Redirect",
+            RestError::Http { .. } => "Http",
+            RestError::LoginRequired => "LoginRequired",
+            RestError::NoClientId => "NoClientId",
+            RestError::NoRefreshToken => "NoRefreshToken",
+            RestError::RenewalUnreachable { .. } => "RenewalUnreachable",
+            RestError::EmptyData => "EmptyData",
+            RestError::DeadlineExceeded => "DeadlineExceeded",
+            RestError::RequestBuild(_) => "RequestBuild",
+            RestError::Json(_) => "Json",
+            RestError::Transport(_) => "Transport",
+            RestError::Base64Decode(_) => "Base64Decode",
+            RestError::Io(_) => "Io",
+            RestError::Other(_) => "Other",
+            RestError::SchemaViolation { .. } => "SchemaViolation",
+            RestError::ChecksumMismatch { .. } => "ChecksumMismatch",
+            RestError::Cancelled => "Cancelled",
+            RestError::JobFailed { .. } => "JobFailed",
+            RestError::JobTimeout { .. } => "JobTimeout",
+            RestError::UploadPart { .. } => "UploadPart",
+            RestError::RateLimited { .. } => "RateLimited",
+            RestError::Correlated { .. } => "Correlated",
+        }
+    }
+
+    /// A multi-line diagnostic of everything known about this error, for
+    /// logs and bug reports
+    ///
+    /// One `name: value` line per known detail: the message, variant, API
+    /// code, HTTP status, request and correlation ids, the server's error
+    /// token, `extra` and exception class, then the chain of underlying
+    /// causes. Credentials are masked as in
+    /// [`RequestTrace::redacted`](crate::RequestTrace::redacted): the
+    /// values of the `_sign`, `access_token` and `refresh_token` parameters
+    /// (also as JSON fields), of the `Authorization` and
+    /// `X-Amz-Security-Token` headers, and of `Bearer` and `Basic`
+    /// credentials, as well as signatures under the
+    /// [renamed parameter](crate::ApiKey::with_param_names) of an API key
+    /// given to a client. Long lines (such as HTML error pages) are
+    /// truncated.
+    pub fn report(&self) -> String {
+        let mut lines = vec![
+            ("error", self.to_string()),
+            ("kind", self.kind().to_string()),
+        ];
+        if let Some(code) = self.status_code() {
+            lines.push(("code", code.to_string()));
+        }
+        lines.push(("http status", self.http_status().to_string()));
+        let response = self.response();
+        let details = [
+            ("request id", self.request_id()),
+            ("correlation id", self.correlation_id()),
+            ("error token", self.error_token()),
+            ("extra", response.and_then(|r| r.extra.as_deref())),
+            ("exception", self.exception()),
+        ];
+        for (name, value) in details {
+            if let Some(value) = value {
+                lines.push((name, value.to_string()));
+            }
+        }
+        if let Some(retry_after) = self.retry_after() {
+            lines.push(("retry after", format!("{}s", retry_after.as_secs())));
+        }
+        let mut source = std::error::Error::source(self);
+        while let Some(cause) = source {
+            lines.push(("caused by", cause.to_string()));
+            source = cause.source();
+        }
+
+        let sign_params = REPORT_SIGN_PARAMS.lock().unwrap().clone();
+        lines
+            .into_iter()
+            .map(|(name, value)| format!("{}: {}", name, report_value(&value, &sign_params)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     /// Get the HTTP status code if this is an API error
     pub fn status_code(&self) -> Option<i32> {
-        match self {
+        match self.inner() {
             RestError::Api { code, .. } => *code,
+            RestError::Redirect { code, .. } => *code,
             RestError::Http { status, .. } => Some(*status as i32),
             _ => None,
         }
     }
 }
 
+/// `value` on one line, with credentials and the `sign_params` signatures
+/// masked and truncated to [`REPORT_LINE_MAX`] characters.
+fn report_value(value: &str, sign_params: &[String]) -> String {
+    let mut line = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    let params = SECRET_PARAMS
+        .into_iter()
+        .chain(sign_params.iter().map(String::as_str));
+    for param in params {
+        mask_after(&mut line, &format!("{}=", param));
+        mask_after(&mut line, &format!("\"{}\":\"", param));
+    }
+    for scheme in AUTH_SCHEMES {
+        mask_after(&mut line, &format!("{} ", scheme));
+    }
+    for header in SECRET_HEADERS {
+        mask_after(&mut line, &format!("{}: ", header));
+        mask_after(&mut line, &format!("\"{}\":\"", header));
+    }
+    if let Some((cut, _)) = line.char_indices().nth(REPORT_LINE_MAX) {
+        line.truncate(cut);
+        line.push_str("...");
+    }
+    line
+}
+
+/// Mask in `line` the value after each occurrence of `marker`, matched
+/// case-insensitively, up to the next separator. An authorization scheme is
+/// left in place, its credentials being masked on their own.
+fn mask_after(line: &mut String, marker: &str) {
+    let marker = marker.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(pos) = line[from..].to_ascii_lowercase().find(&marker) {
+        let start = from + pos + marker.len();
+        let end = line[start..]
+            .find(['&', ' ', ',', '\'', '"'])
+            .map_or(line.len(), |n| start + n);
+        let value = &line[start..end];
+        if !value.is_empty()
+            && value != REDACTED
+            && !AUTH_SCHEMES.iter().any(|s| s.eq_ignore_ascii_case(value))
+        {
+            line.replace_range(start..end, REDACTED);
+        }
+        from = start;
+    }
+}
+
 /// Result type for REST operations
 pub type Result<T> = std::result::Result<T, RestError>;
 
@@ -129,6 +641,292 @@ pub type Result<T> = std::result::Result<T, RestError>;
 mod tests {
     use super::*;
 
+    fn api_error(code: Option<i32>) -> RestError {
+        let response: Response = serde_json::from_value(serde_json::json!({
+            "result": "error",
+            "error": "failed",
+            "code": code,
+        }))
+        .unwrap();
+        RestError::from_response(response)
+    }
+
+    #[test]
+    fn test_http_status() {
+        assert_eq!(api_error(Some(403)).http_status(), 403);
+        assert_eq!(api_error(Some(0)).http_status(), 500);
+        assert_eq!(api_error(None).http_status(), 500);
+        assert_eq!(RestError::http(503, String::new(), None).http_status(), 503);
+        assert_eq!(RestError::http(302, String::new(), None).http_status(), 500);
+        assert_eq!(RestError::LoginRequired.http_status(), 401);
+        assert_eq!(RestError::NoClientId.http_status(), 401);
+        assert_eq!(RestError::NoRefreshToken.http_status(), 401);
+        assert_eq!(RestError::RequestBuild("bad".into()).http_status(), 400);
+        assert_eq!(RestError::DeadlineExceeded.http_status(), 504);
+
+        let timeout = std::io::Error::from(std::io::ErrorKind::TimedOut);
+        assert_eq!(
+            RestError::Transport(rsurl::Error::Io(timeout)).http_status(),
+            504
+        );
+        assert_eq!(
+            RestError::Transport(rsurl::Error::UnexpectedEof).http_status(),
+            502
+        );
+
+        let json = serde_json::from_str::<Response>("{").unwrap_err();
+        assert_eq!(RestError::Json(json).http_status(), 500);
+        assert_eq!(RestError::Io(std::io::Error::other("x")).http_status(), 500);
+        assert_eq!(RestError::Other("x".into()).http_status(), 500);
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(api_error(Some(503)).is_retryable());
+        assert!(api_error(Some(429)).is_retryable());
+        assert!(!api_error(Some(404)).is_retryable());
+        assert!(!api_error(None).is_retryable());
+        assert!(RestError::http(502, String::new(), None).is_retryable());
+        assert!(!RestError::http(400, String::new(), None).is_retryable());
+
+        let io = |kind| RestError::Transport(rsurl::Error::Io(std::io::Error::from(kind)));
+        assert!(io(std::io::ErrorKind::TimedOut).is_retryable());
+        assert!(io(std::io::ErrorKind::ConnectionReset).is_retryable());
+        assert!(!io(std::io::ErrorKind::InvalidInput).is_retryable());
+        assert!(RestError::Transport(rsurl::Error::UnexpectedEof).is_retryable());
+        assert!(!RestError::Transport(rsurl::Error::InvalidUrl("x".into())).is_retryable());
+
+        let json = serde_json::from_str::<Response>("{").unwrap_err();
+        assert!(!RestError::Json(json).is_retryable());
+        assert!(!RestError::LoginRequired.is_retryable());
+        assert!(!RestError::DeadlineExceeded.is_retryable());
+        assert!(RestError::Correlated {
+            correlation_id: "c".to_string(),
+            source: Box::new(api_error(Some(500))),
+        }
+        .is_retryable());
+
+        let part = RestError::UploadPart {
+            part: 2,
+            attempts: 4,
+            source: Box::new(api_error(Some(503))),
+        };
+        assert_eq!(part.status_code(), Some(503));
+        assert!(part
+            .to_string()
+            .contains("part 2 failed after 4 attempt(s)"));
+
+        let limited = RestError::Correlated {
+            correlation_id: "c".to_string(),
+            source: Box::new(RestError::RateLimited {
+                retry_after: Duration::from_secs(30),
+                source: Box::new(api_error(Some(429))),
+            }),
+        };
+        assert_eq!(limited.retry_after(), Some(Duration::from_secs(30)));
+        assert_eq!(limited.status_code(), Some(429));
+        assert!(limited.is_retryable());
+        assert_eq!(api_error(Some(429)).retry_after(), None);
+    }
+
+    #[test]
+    fn test_report() {
+        let response: Response = serde_json::from_value(serde_json::json!({
+            "result": "error",
+            "error": "token expired",
+            "code": 401,
+            "token": "invalid_request_token",
+            "extra": "token_expired",
+            "exception": "Exception\\Token",
+        }))
+        .unwrap();
+        let mut err = RestError::from_response(response);
+        if let RestError::Api { request_id, .. } = &mut err {
+            *request_id = Some("req-1".to_string());
+        }
+        let err = RestError::Correlated {
+            correlation_id: "corr-9".to_string(),
+            source: Box::new(err),
+        };
+
+        assert_eq!(err.request_id(), Some("req-1"));
+        assert_eq!(err.error_token(), Some("invalid_request_token"));
+        assert_eq!(err.exception(), Some("Exception\\Token"));
+        let report = err.report();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "error: REST API error: token expired (correlation id: corr-9)",
+                "kind: Api",
+                "code: 401",
+                "http status: 401",
+                "request id: req-1",
+                "correlation id: corr-9",
+                "error token: invalid_request_token",
+                "extra: token_expired",
+                "exception: Exception\\Token",
+                "caused by: REST API error: token expired",
+            ]
+        );
+
+        let page = format!("<p>see /x?_sign=SECRET&a=1</p>{}", "x".repeat(1000));
+        let report = RestError::http(502, page, None).report();
+        assert!(!report.contains("SECRET"), "{}", report);
+        assert!(report.contains("_sign=[redacted]&a=1"), "{}", report);
+        assert!(report.lines().next().unwrap().ends_with("..."));
+        assert!(report.contains("\nkind: Http\ncode: 502\nhttp status: 502"));
+
+        // Tokens are masked in query strings, JSON, and headers.
+        let body = concat!(
+            r#"{"access_token":"AT-1","refresh_token":"RT-1"} "#,
+            "GET /x?refresh_token=RT-2&a=1 Authorization: Bearer BT-1 ",
+            "x-amz-security-token: ST-1, authorization: Basic dXNlcg==",
+        );
+        let report = RestError::http(400, body.to_string(), None).report();
+        for secret in ["AT-1", "RT-1", "RT-2", "BT-1", "ST-1", "dXNlcg=="] {
+            assert!(!report.contains(secret), "{}: {}", secret, report);
+        }
+        assert!(
+            report.contains("Authorization: Bearer [redacted] "),
+            "{}",
+            report
+        );
+        assert!(
+            report.contains("refresh_token=[redacted]&a=1"),
+            "{}",
+            report
+        );
+
+        // A renamed signature is masked once a client uses it.
+        let names = crate::ParamNames {
+            sign: "report_sig".to_string(),
+            ..crate::ParamNames::default()
+        };
+        let err = RestError::http(
+            502,
+            "see /x?report_sig=SECRET&_sign=OTHER".to_string(),
+            None,
+        );
+        assert!(err.report().contains("report_sig=SECRET"));
+        let key = crate::ApiKey::new("key".to_string(), &"A".repeat(43)).unwrap();
+        let _ = crate::Client::new().with_api_key(key.with_param_names(names));
+        let report = err.report();
+        assert!(
+            !report.contains("SECRET") && !report.contains("OTHER"),
+            "{}",
+            report
+        );
+    }
+
+    #[test]
+    fn test_is_maintenance() {
+        let page = "<html><h1>Down for Maintenance</h1></html>".to_string();
+        assert!(RestError::http(503, page.clone(), None).is_maintenance());
+        assert!(RestError::http(503, String::new(), None).is_maintenance());
+        assert!(RestError::http(502, page.clone(), None).is_maintenance());
+        assert!(!RestError::http(502, String::new(), None).is_maintenance());
+        assert!(!RestError::http(404, page, None).is_maintenance());
+        assert!(api_error(Some(503)).is_maintenance());
+        assert!(!api_error(Some(500)).is_maintenance());
+        assert!(!RestError::LoginRequired.is_maintenance());
+    }
+
+    #[test]
+    fn test_redirect() {
+        let response: Response = serde_json::from_value(serde_json::json!({
+            "result": "redirect",
+            "redirect_url": "/_special/rest/Export/Resume?batch=2&format=csv",
+            "redirect_code": 303,
+            "data": {"cursor": "c-17", "batch": 3},
+        }))
+        .unwrap();
+        let err = RestError::from_response(response);
+
+        let RestError::Redirect {
+            ref url,
+            code,
+            ref response,
+        } = err
+        else {
+            panic!("expected a redirect, got {:?}", err);
+        };
+        assert_eq!(
+            url.as_deref(),
+            Some("/_special/rest/Export/Resume?batch=2&format=csv")
+        );
+        assert_eq!(code, Some(303));
+        assert_eq!(response.data.as_ref().unwrap()["cursor"], "c-17");
+        assert_eq!(err.status_code(), Some(303));
+        assert!(err.to_string().contains("Export/Resume"), "{}", err);
+
+        let params = err.redirect_params().unwrap();
+        assert_eq!(
+            Value::Object(params),
+            serde_json::json!({"batch": 3, "format": "csv", "cursor": "c-17"})
+        );
+        assert_eq!(api_error(Some(400)).redirect_params(), None);
+    }
+
+    #[test]
+    fn test_auth_error_kind() {
+        assert_eq!(
+            RestError::LoginRequired.auth_error_kind(),
+            Some(AuthErrorKind::Missing)
+        );
+        assert_eq!(
+            RestError::NoRefreshToken.auth_error_kind(),
+            Some(AuthErrorKind::Expired)
+        );
+        assert_eq!(
+            RestError::NoClientId.auth_error_kind(),
+            Some(AuthErrorKind::Misconfigured)
+        );
+        assert_eq!(
+            api_error(Some(401)).auth_error_kind(),
+            Some(AuthErrorKind::Invalid)
+        );
+        assert_eq!(
+            RestError::http(401, String::new(), None).auth_error_kind(),
+            Some(AuthErrorKind::Invalid)
+        );
+
+        let expired: Response = serde_json::from_value(serde_json::json!({
+            "result": "error",
+            "token": "invalid_request_token",
+            "extra": "token_expired",
+        }))
+        .unwrap();
+        assert_eq!(
+            RestError::from_response(expired).auth_error_kind(),
+            Some(AuthErrorKind::Expired)
+        );
+
+        assert!(!api_error(Some(403)).is_auth_error());
+        assert!(!api_error(None).is_auth_error());
+        assert!(!RestError::EmptyData.is_auth_error());
+        assert!(RestError::Correlated {
+            correlation_id: "c".to_string(),
+            source: Box::new(RestError::LoginRequired),
+        }
+        .is_auth_error());
+    }
+
+    #[test]
+    fn test_correlated() {
+        let err = RestError::Correlated {
+            correlation_id: "req-42".to_string(),
+            source: Box::new(api_error(Some(404))),
+        };
+        assert_eq!(err.correlation_id(), Some("req-42"));
+        assert!(err.is_not_found());
+        assert_eq!(err.status_code(), Some(404));
+        assert_eq!(err.http_status(), 404);
+        assert!(matches!(err.inner(), RestError::Api { .. }));
+        assert!(err.to_string().contains("req-42"));
+        assert_eq!(api_error(None).correlation_id(), None);
+    }
+
     #[test]
     fn test_error_permission_denied() {
         let response = Response {
@@ -146,6 +944,9 @@ mod tests {
             redirect_url: None,
             redirect_code: None,
             request_id: None,
+            status: 0,
+            from_cache: false,
+            upload_sha256: None,
         };
 
         let error = RestError::from_response(response);
@@ -169,6 +970,9 @@ mod tests {
             redirect_url: None,
             redirect_code: None,
             request_id: None,
+            status: 0,
+            from_cache: false,
+            upload_sha256: None,
         };
 
         let error = RestError::from_response(response);
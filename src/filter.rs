@@ -0,0 +1,350 @@
+//! Builder for the filter parameters of list endpoints.
+//!
+//! List endpoints take filters as an object mapping each field to its
+//! conditions, one `$`-prefixed operator per condition:
+//!
+//! ```json
+//! {"age": {"$gt": 18, "$lt": 65}, "status": {"$eq": "active"}}
+//! ```
+//!
+//! | Method             | Operator | Condition                      |
+//! |--------------------|----------|--------------------------------|
+//! | [`eq`](FieldFilter::eq)       | `$eq`    | equal to the value             |
+//! | [`ne`](FieldFilter::ne)       | `$ne`    | not equal to the value         |
+//! | [`gt`](FieldFilter::gt)       | `$gt`    | greater than the value         |
+//! | [`lt`](FieldFilter::lt)       | `$lt`    | less than the value            |
+//! | [`is_in`](FieldFilter::is_in) | `$in`    | equal to one of a list         |
+//! | [`like`](FieldFilter::like)   | `$like`  | matches a SQL `LIKE` pattern   |
+//!
+//! Conditions on the same field are combined in one object. When both sides
+//! of [`Filter::and`] use the same operator on a field, the conditions are
+//! merged so both still hold: `$gt` keeps the larger number, `$lt` the
+//! smaller one, `$in` the values in both lists and two `$ne` become a `$nin`
+//! list of both values. Conditions that cannot share one object, such as two
+//! `$like` patterns, go to a `$and` list of filters that must all match:
+//!
+//! ```json
+//! {"name": {"$like": "a%"}, "$and": [{"name": {"$like": "%z"}}]}
+//! ```
+//!
+//! A [`Filter`] serializes to this object, so it can be sent as the
+//! parameters of a request:
+//!
+//! ```no_run
+//! use klbfw::{Client, Filter};
+//!
+//! # fn main() -> klbfw::Result<()> {
+//! let filter = Filter::field("age")
+//!     .gt(18)
+//!     .and(Filter::field("status").eq("active"));
+//! let users: Vec<serde_json::Value> = Client::new().apply("User", "GET", &filter)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{RestError, Result};
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+
+/// Conditions on the fields of a list, see the [module documentation](self)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Filter {
+    fields: Map<String, Value>,
+    /// Conditions kept apart, sent as `$and`
+    all_of: Vec<Map<String, Value>>,
+}
+
+/// A field awaiting its condition, from [`Filter::field`]
+#[derive(Debug, Clone)]
+pub struct FieldFilter {
+    field: String,
+}
+
+impl Filter {
+    /// Start a condition on `field`
+    pub fn field(field: impl Into<String>) -> FieldFilter {
+        FieldFilter {
+            field: field.into(),
+        }
+    }
+
+    /// Combine with the conditions of `other`; both must hold
+    ///
+    /// An operator used on the same field by both filters is merged, see the
+    /// [module documentation](self).
+    pub fn and(mut self, other: Filter) -> Filter {
+        for (field, conditions) in other.fields {
+            if let Value::Object(conditions) = conditions {
+                self.add(field, conditions);
+            }
+        }
+        self.all_of.extend(other.all_of);
+        self
+    }
+
+    /// Like [`and`](Self::and), but fails with [`RestError::RequestBuild`]
+    /// when no row can match both filters: `$eq` on a field with two
+    /// different values, or `$in` lists with no value in common.
+    pub fn try_and(self, other: Filter) -> Result<Filter> {
+        for (field, conditions) in &other.fields {
+            let (Some(Value::Object(existing)), Value::Object(conditions)) =
+                (self.fields.get(field), conditions)
+            else {
+                continue;
+            };
+            for (operator, value) in conditions {
+                let Some(current) = existing.get(operator) else {
+                    continue;
+                };
+                let disjoint = match (operator.as_str(), current, value) {
+                    ("$eq", _, _) => current != value,
+                    ("$in", Value::Array(a), Value::Array(b)) => !a.iter().any(|v| b.contains(v)),
+                    _ => false,
+                };
+                if disjoint {
+                    return Err(RestError::RequestBuild(format!(
+                        "conflicting {} conditions on {}: {} and {}",
+                        operator, field, current, value
+                    )));
+                }
+            }
+        }
+        Ok(self.and(other))
+    }
+
+    /// Add the `conditions` on `field`, merging repeated operators.
+    fn add(&mut self, field: String, conditions: Map<String, Value>) {
+        let Some(Value::Object(existing)) = self.fields.get_mut(&field) else {
+            self.fields.insert(field, Value::Object(conditions));
+            return;
+        };
+        let mut apart = Map::new();
+        for (operator, value) in conditions {
+            let Some(current) = existing.remove(&operator) else {
+                existing.insert(operator, value);
+                continue;
+            };
+            if operator == "$ne" && current != value {
+                // Neither value: both go to `$nin`.
+                let mut values = match existing.remove("$nin") {
+                    Some(Value::Array(values)) => values,
+                    _ => Vec::new(),
+                };
+                for value in [current, value] {
+                    if !values.contains(&value) {
+                        values.push(value);
+                    }
+                }
+                existing.insert("$nin".to_string(), Value::Array(values));
+                continue;
+            }
+            match merge(&operator, current, value) {
+                Ok(merged) => {
+                    existing.insert(operator, merged);
+                }
+                Err((current, value)) => {
+                    existing.insert(operator.clone(), current);
+                    apart.insert(operator, value);
+                }
+            }
+        }
+        if !apart.is_empty() {
+            let mut filter = Map::new();
+            filter.insert(field, Value::Object(apart));
+            self.all_of.push(filter);
+        }
+    }
+
+    /// Whether no condition was added
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// The filter as a JSON object
+    pub fn to_value(&self) -> Value {
+        let mut fields = self.fields.clone();
+        if !self.all_of.is_empty() {
+            let all_of = self.all_of.iter().cloned().map(Value::Object).collect();
+            fields.insert("$and".to_string(), Value::Array(all_of));
+        }
+        Value::Object(fields)
+    }
+}
+
+impl FieldFilter {
+    fn condition(self, operator: &str, value: Value) -> Filter {
+        let mut conditions = Map::new();
+        conditions.insert(operator.to_string(), value);
+        let mut fields = Map::new();
+        fields.insert(self.field, Value::Object(conditions));
+        Filter {
+            fields,
+            all_of: Vec::new(),
+        }
+    }
+
+    /// The field equals `value` (`$eq`)
+    pub fn eq(self, value: impl Into<Value>) -> Filter {
+        self.condition("$eq", value.into())
+    }
+
+    /// The field differs from `value` (`$ne`)
+    pub fn ne(self, value: impl Into<Value>) -> Filter {
+        self.condition("$ne", value.into())
+    }
+
+    /// The field is greater than `value` (`$gt`)
+    pub fn gt(self, value: impl Into<Value>) -> Filter {
+        self.condition("$gt", value.into())
+    }
+
+    /// The field is less than `value` (`$lt`)
+    pub fn lt(self, value: impl Into<Value>) -> Filter {
+        self.condition("$lt", value.into())
+    }
+
+    /// The field equals one of `values` (`$in`)
+    pub fn is_in<V: Into<Value>>(self, values: impl IntoIterator<Item = V>) -> Filter {
+        let values = values.into_iter().map(Into::into).collect();
+        self.condition("$in", Value::Array(values))
+    }
+
+    /// The field matches the SQL `LIKE` pattern `pattern`, where `%` stands
+    /// for any run of characters and `_` for one (`$like`)
+    pub fn like(self, pattern: impl Into<String>) -> Filter {
+        self.condition("$like", Value::String(pattern.into()))
+    }
+}
+
+/// Merge two `operator` conditions into one implying both, or give them back
+/// if they cannot be.
+fn merge(
+    operator: &str,
+    current: Value,
+    value: Value,
+) -> std::result::Result<Value, (Value, Value)> {
+    if current == value {
+        return Ok(current);
+    }
+    match (operator, &current, &value) {
+        ("$gt" | "$lt", Value::Number(a), Value::Number(b)) => {
+            if let (Some(a), Some(b)) = (a.as_f64(), b.as_f64()) {
+                // The larger lower bound, the smaller upper bound.
+                let (smaller, larger) = if b > a {
+                    (current, value)
+                } else {
+                    (value, current)
+                };
+                return Ok(if operator == "$gt" { larger } else { smaller });
+            }
+        }
+        ("$in", Value::Array(a), Value::Array(b)) => {
+            let both = a.iter().filter(|v| b.contains(v)).cloned().collect();
+            return Ok(Value::Array(both));
+        }
+        _ => {}
+    }
+    Err((current, value))
+}
+
+impl Serialize for Filter {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
+
+impl From<Filter> for Value {
+    fn from(filter: Filter) -> Self {
+        filter.to_value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_filter_shape() {
+        let filter = [
+            Filter::field("status").eq("active"),
+            Filter::field("age").lt(65),
+            Filter::field("role").is_in(["admin", "staff"]),
+            Filter::field("name").like("jo%"),
+            Filter::field("deleted").ne(true),
+        ]
+        .into_iter()
+        .fold(Filter::field("age").gt(18), Filter::and);
+
+        assert_eq!(
+            serde_json::to_value(&filter).unwrap(),
+            json!({
+                "age": {"$gt": 18, "$lt": 65},
+                "status": {"$eq": "active"},
+                "role": {"$in": ["admin", "staff"]},
+                "name": {"$like": "jo%"},
+                "deleted": {"$ne": true},
+            })
+        );
+        assert_eq!(Value::from(filter.clone()), filter.to_value());
+    }
+
+    #[test]
+    fn test_filter_repeated_operator() {
+        // Merged so both conditions hold.
+        let and = |a: Filter, b: Filter| a.and(b).to_value();
+        let age = || Filter::field("age");
+        assert_eq!(and(age().gt(2), age().gt(1)), json!({"age": {"$gt": 2}}));
+        assert_eq!(
+            and(age().gt(1).and(age().lt(10)), age().gt(2.5)),
+            json!({"age": {"$gt": 2.5, "$lt": 10}})
+        );
+        assert_eq!(and(age().lt(5), age().lt(7)), json!({"age": {"$lt": 5}}));
+        assert_eq!(
+            and(age().is_in([1, 2, 3]), age().is_in([3, 2, 4])),
+            json!({"age": {"$in": [2, 3]}})
+        );
+        assert_eq!(and(age().eq(3), age().eq(3)), json!({"age": {"$eq": 3}}));
+        assert_eq!(
+            age()
+                .ne(1)
+                .and(age().ne(2))
+                .and(age().ne(3))
+                .and(age().ne(1))
+                .to_value(),
+            json!({"age": {"$nin": [1, 2, 3]}})
+        );
+
+        // Kept apart when they cannot share one object.
+        let name = || Filter::field("name");
+        assert_eq!(
+            name()
+                .like("a%")
+                .and(name().like("%z"))
+                .and(age().gt(1))
+                .to_value(),
+            json!({
+                "name": {"$like": "a%"},
+                "age": {"$gt": 1},
+                "$and": [{"name": {"$like": "%z"}}],
+            })
+        );
+        assert_eq!(
+            and(age().gt("a"), age().gt("b").and(age().gt("c"))),
+            json!({"age": {"$gt": "a"}, "$and": [{"age": {"$gt": "b"}}, {"age": {"$gt": "c"}}]})
+        );
+
+        // try_and rejects conditions no row can match.
+        let err = age().eq(1).try_and(age().eq(2)).unwrap_err();
+        assert!(matches!(err, RestError::RequestBuild(_)), "{:?}", err);
+        assert!(err.to_string().contains("$eq conditions on age"), "{}", err);
+        assert!(age().is_in([1]).try_and(age().is_in([2])).is_err());
+        assert_eq!(
+            name().like("a%").try_and(name().like("%z")).unwrap(),
+            name().like("a%").and(name().like("%z"))
+        );
+
+        assert!(Filter::default().is_empty());
+        assert_eq!(Filter::default().to_value(), json!({}));
+    }
+}
@@ -0,0 +1,216 @@
+//! Structured connectivity diagnostics.
+//!
+//! [`Client::health`] checks in one call whether the API host is reachable,
+//! whether its TLS certificate is valid, whether the configured credentials
+//! are accepted, the request latency and
+//! how far the local clock is from the server's, and reports everything in a
+//! [`HealthReport`]. Clock skew matters because signed requests carry a
+//! timestamp the server checks.
+//!
+//! [`Client::health`]: crate::Client::health
+
+use crate::response::Response;
+use crate::rest::{AuthMethod, Client};
+use crate::time::Time;
+use chrono::Utc;
+use std::time::{Duration, Instant};
+
+/// Authenticated endpoint used for the check; cheap and always available.
+const HEALTH_PATH: &str = "User:get";
+/// Largest clock skew [`HealthReport::is_healthy`] accepts.
+const MAX_CLOCK_SKEW: Duration = Duration::from_secs(5 * 60);
+
+/// Result of [`Client::health`](crate::Client::health)
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct HealthReport {
+    /// API host checked
+    pub base_url: String,
+    /// Whether the host answered at all
+    pub reachable: bool,
+    /// Whether the host presented a valid certificate, from a separate TLS
+    /// handshake with verification enabled; `None` for plain HTTP, when no
+    /// handshake completed, or when the transport cannot tell (see
+    /// [`Transport::check_tls`](crate::transport::Transport::check_tls))
+    pub tls_valid: Option<bool>,
+    /// Configured authentication, if any
+    pub auth_method: Option<AuthMethod>,
+    /// Whether the server accepted the credentials; `None` without
+    /// credentials or when the check failed for another reason
+    pub auth_valid: Option<bool>,
+    /// Time taken by the check request
+    pub latency: Option<Duration>,
+    /// Server time, from the response's `time` field
+    pub server_time: Option<Time>,
+    /// Local clock minus server clock; positive when the local clock is
+    /// ahead
+    pub clock_skew: Option<chrono::Duration>,
+    /// Error of the check request, if it failed
+    pub error: Option<String>,
+}
+
+impl HealthReport {
+    /// Whether the host is reachable, its certificate is not found invalid,
+    /// the credentials (if any) are not rejected, and the clocks differ by
+    /// at most 5 minutes
+    pub fn is_healthy(&self) -> bool {
+        let skew_ok = self
+            .clock_skew
+            .and_then(|skew| skew.abs().to_std().ok())
+            .is_none_or(|skew| skew <= MAX_CLOCK_SKEW);
+        self.reachable && self.tls_valid != Some(false) && self.auth_valid != Some(false) && skew_ok
+    }
+}
+
+/// Run the health check for `ctx`.
+pub(crate) fn check(ctx: &Client) -> HealthReport {
+    // A cached answer would say nothing of the host, and carries no status.
+    let ctx = ctx.without_cache();
+    let base_url = ctx.config().base_url();
+    let auth_method = ctx.auth_method();
+    let tls_valid = ctx.check_tls();
+
+    let start = Instant::now();
+    let (result, trace) = ctx.do_request_traced(HEALTH_PATH, "GET", ());
+    let latency = start.elapsed();
+    let received = Utc::now();
+
+    let reachable = trace.status.is_some();
+    let server_time = Response::parse_body(&trace.response_body)
+        .ok()
+        .and_then(|(response, _)| response.time)
+        .and_then(|time| serde_json::from_value::<Time>(time).ok());
+    // The server stamped its time around the middle of the round trip.
+    let clock_skew = server_time.map(|time| {
+        let midpoint = received - chrono::Duration::from_std(latency / 2).unwrap_or_default();
+        midpoint - time.0
+    });
+
+    let (auth_valid, error) = match &result {
+        Ok(_) => (auth_method.map(|_| true), None),
+        Err(e) => {
+            let valid = match auth_method {
+                Some(_) if e.is_auth_error() => Some(false),
+                _ => None,
+            };
+            (valid, Some(e.to_string()))
+        }
+    };
+
+    HealthReport {
+        base_url,
+        reachable,
+        tls_valid,
+        auth_method,
+        auth_valid,
+        latency: reachable.then_some(latency),
+        server_time,
+        clock_skew,
+        error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::DiskCache;
+    use crate::error::{RestError, Result};
+    use crate::token::Token;
+    use crate::transport::testing::Fixed;
+    use crate::transport::{HttpRequest, HttpResponse, Transport};
+    use std::sync::Arc;
+
+    /// Fails every request as if the host were down.
+    struct Down;
+
+    impl Transport for Down {
+        fn send(&self, _request: HttpRequest) -> Result<HttpResponse> {
+            Err(RestError::Transport(rsurl::Error::Io(
+                std::io::Error::from(std::io::ErrorKind::ConnectionRefused),
+            )))
+        }
+    }
+
+    /// Answers like a reachable host whose certificate does not verify.
+    struct BadCertificate;
+
+    impl Transport for BadCertificate {
+        fn send(&self, _request: HttpRequest) -> Result<HttpResponse> {
+            let body = r#"{"result":"success","data":{}}"#;
+            Ok(HttpResponse::new(200, Vec::new(), body.into()))
+        }
+
+        fn check_tls(&self, _url: &str, _timeout: Duration) -> Option<bool> {
+            Some(false)
+        }
+    }
+
+    fn client(transport: impl Transport + 'static) -> Client {
+        Client::new()
+            .with_token(Token::from_bearer("Bearer abc").unwrap())
+            .with_transport(Arc::new(transport))
+    }
+
+    #[test]
+    fn test_health_ok() {
+        let server = Utc::now().timestamp() - 3600;
+        let body = format!(
+            r#"{{"result":"success","data":{{}},"time":{{"unix":{},"us":0}}}}"#,
+            server
+        );
+        let report = client(Fixed(200, body)).health();
+
+        assert!(report.reachable);
+        assert_eq!(report.tls_valid, None, "canned responses carry no TLS");
+        assert_eq!(report.auth_method, Some(AuthMethod::Token));
+        assert_eq!(report.auth_valid, Some(true));
+        assert_eq!(report.server_time.map(|t| t.unix()), Some(server));
+        let skew = report.clock_skew.unwrap().num_seconds();
+        assert!((3599..=3601).contains(&skew), "{}", skew);
+        assert!(!report.is_healthy(), "an hour of skew is unhealthy");
+    }
+
+    #[test]
+    fn test_health_skips_cache() {
+        let cache = tempfile::tempdir().unwrap();
+        let ctx = client(Fixed(200, r#"{"result":"success","data":{}}"#.to_string())).with_cache(
+            Arc::new(DiskCache::new(cache.path(), Duration::from_secs(60))),
+        );
+        ctx.do_request(HEALTH_PATH, "GET", ()).unwrap();
+        let report = ctx.health();
+        assert!(report.reachable);
+        assert!(report.latency.is_some());
+        assert!(report.is_healthy());
+    }
+
+    #[test]
+    fn test_health_rejected_credentials() {
+        let report = client(Fixed(
+            401,
+            r#"{"result":"error","error":"denied","code":401}"#.to_string(),
+        ))
+        .health();
+        assert!(report.reachable);
+        assert_eq!(report.auth_valid, Some(false));
+        assert!(report.error.is_some());
+        assert!(!report.is_healthy());
+    }
+
+    #[test]
+    fn test_health_invalid_certificate() {
+        let report = client(BadCertificate).health();
+        assert!(report.reachable);
+        assert_eq!(report.tls_valid, Some(false));
+        assert_eq!(report.auth_valid, Some(true));
+        assert!(!report.is_healthy());
+    }
+
+    #[test]
+    fn test_health_unreachable() {
+        let report = client(Down).health();
+        assert!(!report.reachable);
+        assert_eq!(report.auth_valid, None);
+        assert_eq!(report.latency, None);
+        assert!(!report.is_healthy());
+    }
+}
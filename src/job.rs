@@ -0,0 +1,382 @@
+//! Long-running server tasks.
+//!
+//! Endpoints starting work that takes a while answer right away, with a `job`
+//! object in the response describing the task: its id, status and progress.
+//! The task's status is then polled from its own endpoint until it finishes.
+//! [`Response::job_info`](crate::Response::job_info) reads the `job` object
+//! and [`Client::wait_for_job`] runs the polling loop:
+//!
+//! ```no_run
+//! # fn main() -> klbfw::Result<()> {
+//! let ctx = klbfw::Client::new();
+//! let response = ctx.do_request("Video/vid-1:transcode", "POST", ())?;
+//! if let Some(job) = response.job_info() {
+//!     let done = ctx.wait_for_job(&job)?;
+//!     println!("{:?}", done.data);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{RestError, Result};
+use crate::response::{take_field, Response};
+use crate::rest::Client;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::time::{Duration, Instant};
+
+/// Endpoint polled for a job's status, `{id}` standing for the job id.
+const DEFAULT_STATUS_PATH: &str = "Job/{id}";
+/// Default time between two polls of a job's status.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Default time to wait for a job before giving up.
+const DEFAULT_JOB_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Names of the job id, first one preferred.
+const ID_KEYS: [&str; 3] = ["id", "Job__", "job_id"];
+/// Names of the job status.
+const STATUS_KEYS: [&str; 2] = ["status", "state"];
+/// Names of the status message.
+const MESSAGE_KEYS: [&str; 2] = ["message", "error"];
+
+/// Statuses of jobs that completed successfully, matched case-insensitively.
+const DONE_STATUSES: [&str; 6] = [
+    "done",
+    "complete",
+    "completed",
+    "success",
+    "succeeded",
+    "finished",
+];
+/// Statuses of jobs that stopped without completing.
+const FAILED_STATUSES: [&str; 5] = ["failed", "failure", "error", "cancelled", "canceled"];
+
+/// The `job` object of a response starting a long-running task
+///
+/// Like [`Paging`](crate::Paging), accepts the field name variants used
+/// across endpoints: `id` (or `Job__`, `job_id`), `status` (`state`) and
+/// `message` (`error`). `progress` may be sent as a string. Other fields are
+/// kept in `extra`. An object without an id is not a job.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "Map<String, Value>")]
+#[non_exhaustive]
+pub struct Job {
+    /// Job id
+    pub id: String,
+    /// Status as reported by the server, e.g. `pending`, `running` or `done`
+    pub status: String,
+    /// Progress as reported by the server, if any
+    pub progress: Option<f64>,
+    /// Status message, typically the reason of a failure
+    pub message: Option<String>,
+    /// Any other fields
+    pub extra: Map<String, Value>,
+}
+
+impl TryFrom<Map<String, Value>> for Job {
+    type Error = String;
+
+    fn try_from(mut map: Map<String, Value>) -> std::result::Result<Self, Self::Error> {
+        let mut take = |keys: &[&str]| take_field(&mut map, keys);
+        let text = |value: Option<Value>| match value? {
+            Value::String(s) => Some(s),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        };
+
+        let id = text(take(&ID_KEYS))
+            .filter(|id| !id.is_empty())
+            .ok_or("job has no id")?;
+        let status = text(take(&STATUS_KEYS)).unwrap_or_default();
+        let message = text(take(&MESSAGE_KEYS));
+        let progress = match take(&["progress"]) {
+            Some(Value::Number(n)) => n.as_f64(),
+            Some(Value::String(s)) => s.trim().parse().ok(),
+            _ => None,
+        };
+
+        Ok(Job {
+            id,
+            status,
+            progress,
+            message,
+            extra: map,
+        })
+    }
+}
+
+impl Job {
+    /// Read a job from a `job` object, or `None` if `value` is not one.
+    pub(crate) fn from_value(value: &Value) -> Option<Self> {
+        value.as_object().cloned()?.try_into().ok()
+    }
+
+    /// Whether the job completed successfully
+    ///
+    /// True for the statuses `done`, `complete`, `completed`, `success`,
+    /// `succeeded` and `finished`, in any case.
+    pub fn is_done(&self) -> bool {
+        DONE_STATUSES
+            .iter()
+            .any(|s| self.status.eq_ignore_ascii_case(s))
+    }
+
+    /// Whether the job stopped without completing
+    ///
+    /// True for the statuses `failed`, `failure`, `error`, `cancelled` and
+    /// `canceled`, in any case.
+    pub fn is_failed(&self) -> bool {
+        FAILED_STATUSES
+            .iter()
+            .any(|s| self.status.eq_ignore_ascii_case(s))
+    }
+
+    /// Whether the job stopped, successfully or not
+    pub fn is_finished(&self) -> bool {
+        self.is_done() || self.is_failed()
+    }
+}
+
+/// How [`Client::wait_for_job_with_options`] polls a job
+#[derive(Debug, Clone)]
+pub struct JobWaitOptions {
+    poll_interval: Duration,
+    timeout: Duration,
+    status_path: String,
+}
+
+impl Default for JobWaitOptions {
+    fn default() -> Self {
+        JobWaitOptions {
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            timeout: DEFAULT_JOB_TIMEOUT,
+            status_path: DEFAULT_STATUS_PATH.to_string(),
+        }
+    }
+}
+
+impl JobWaitOptions {
+    /// Poll `Job/{id}` every 2 seconds, for up to 10 minutes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wait `interval` between two polls of the job's status
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Give up with [`RestError::JobTimeout`] once the job has not finished
+    /// after `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Poll the job's status at `path`, where `{id}` stands for the job id
+    ///
+    /// The endpoint is requested with `GET` and must answer with the job,
+    /// either as its `job` object or as its `data`.
+    pub fn with_status_path(mut self, path: impl Into<String>) -> Self {
+        self.status_path = path.into();
+        self
+    }
+
+    /// Time between two polls
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    /// Time to wait for the job before giving up
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Endpoint polled for the job's status
+    pub fn status_path(&self) -> &str {
+        &self.status_path
+    }
+}
+
+/// Poll the status of `job` until it finishes, see [`Client::wait_for_job`].
+pub(crate) fn wait(ctx: &Client, job: &Job, options: &JobWaitOptions) -> Result<Response> {
+    let path = options.status_path.replace("{id}", &job.id);
+    // A timeout too far to represent means no deadline.
+    let deadline = Instant::now().checked_add(options.timeout);
+    // A cached status would never change.
+    let ctx = ctx.without_cache();
+    loop {
+        let response = ctx.do_request(&path, "GET", ())?;
+        let current = response
+            .job_info()
+            .or_else(|| response.data.as_ref().and_then(Job::from_value))
+            .ok_or_else(|| {
+                RestError::Other(format!("status of job {} has no job object", job.id))
+            })?;
+        if current.is_done() {
+            return Ok(response);
+        }
+        if current.is_failed() {
+            return Err(RestError::JobFailed {
+                id: current.id,
+                status: current.status,
+                message: current.message,
+                response: Box::new(response),
+            });
+        }
+
+        let now = Instant::now();
+        if deadline.is_some_and(|deadline| now >= deadline) {
+            return Err(RestError::JobTimeout {
+                id: job.id.clone(),
+                timeout: options.timeout,
+            });
+        }
+        let wait = deadline.map_or(options.poll_interval, |deadline| {
+            options.poll_interval.min(deadline - now)
+        });
+        std::thread::sleep(wait);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::DiskCache;
+    use crate::transport::{HttpRequest, HttpResponse, Transport};
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    /// Reports a job as running until `polls` status requests were made,
+    /// then with the final status.
+    struct Jobs {
+        requests: Mutex<Vec<HttpRequest>>,
+        polls: usize,
+        status: &'static str,
+    }
+
+    impl Jobs {
+        fn new(polls: usize, status: &'static str) -> Arc<Self> {
+            Arc::new(Jobs {
+                requests: Mutex::new(Vec::new()),
+                polls,
+                status,
+            })
+        }
+    }
+
+    impl Transport for Jobs {
+        fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+            let mut requests = self.requests.lock().unwrap();
+            requests.push(request);
+            let status = if requests.len() < self.polls {
+                "running"
+            } else {
+                self.status
+            };
+            let body = json!({
+                "result": "success",
+                "data": {"Job__": "job-1", "status": status, "message": "disk full"},
+            });
+            Ok(HttpResponse::new(
+                200,
+                Vec::new(),
+                body.to_string().into_bytes(),
+            ))
+        }
+    }
+
+    fn options() -> JobWaitOptions {
+        JobWaitOptions::new().with_poll_interval(Duration::from_millis(1))
+    }
+
+    #[test]
+    fn test_job_info() {
+        let mut response = Response::from_raw(Value::Null);
+        assert_eq!(response.job_info(), None);
+
+        response.job = Some(json!({
+            "job_id": 42,
+            "state": "Running",
+            "progress": "0.5",
+            "queue": "video",
+        }));
+        let job = response.job_info().unwrap();
+        assert_eq!(job.id, "42");
+        assert_eq!(job.status, "Running");
+        assert_eq!(job.progress, Some(0.5));
+        assert_eq!(job.message, None);
+        assert_eq!(job.extra.get("queue"), Some(&json!("video")));
+        assert!(!job.is_finished());
+
+        response.job = Some(json!({"status": "done"}));
+        assert_eq!(response.job_info(), None);
+
+        for (status, done, failed) in [
+            ("Completed", true, false),
+            ("success", true, false),
+            ("CANCELLED", false, true),
+            ("pending", false, false),
+        ] {
+            let job = Job::from_value(&json!({"id": "j", "status": status})).unwrap();
+            assert_eq!(
+                (job.is_done(), job.is_failed()),
+                (done, failed),
+                "{}",
+                status
+            );
+        }
+    }
+
+    #[test]
+    fn test_wait_for_job() {
+        let job = Job::from_value(&json!({"id": "job-1", "status": "pending"})).unwrap();
+
+        // Polls go to the server even on a client with a cache.
+        let jobs = Jobs::new(3, "done");
+        let cache = tempfile::tempdir().unwrap();
+        let ctx = Client::new()
+            .with_transport(jobs.clone())
+            .with_cache(Arc::new(DiskCache::new(
+                cache.path(),
+                Duration::from_secs(60),
+            )));
+        let response = ctx.wait_for_job_with_options(&job, &options()).unwrap();
+        assert_eq!(response.get_string("status").as_deref(), Some("done"));
+        let requests = jobs.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests.iter().all(|r| r.method == "GET"));
+        assert!(requests[0].url.contains("/_special/rest/Job/job-1"));
+
+        let jobs = Jobs::new(2, "failed");
+        let ctx = Client::new().with_transport(jobs.clone());
+        let err = ctx
+            .wait_for_job_with_options(&job, &options().with_status_path("Queue/{id}"))
+            .unwrap_err();
+        let RestError::JobFailed { id, message, .. } = &err else {
+            panic!("expected JobFailed, got {:?}", err);
+        };
+        assert_eq!(
+            (id.as_str(), message.as_deref()),
+            ("job-1", Some("disk full"))
+        );
+        assert!(jobs.requests.lock().unwrap()[0]
+            .url
+            .contains("/Queue/job-1"));
+
+        // An unrepresentable timeout waits without a deadline.
+        let jobs = Jobs::new(2, "done");
+        let ctx = Client::new().with_transport(jobs.clone());
+        ctx.wait_for_job_with_options(&job, &options().with_timeout(Duration::MAX))
+            .unwrap();
+
+        let jobs = Jobs::new(usize::MAX, "done");
+        let ctx = Client::new().with_transport(jobs.clone());
+        let err = ctx
+            .wait_for_job_with_options(&job, &options().with_timeout(Duration::from_millis(20)))
+            .unwrap_err();
+        assert!(matches!(err, RestError::JobTimeout { .. }), "{:?}", err);
+        assert!(jobs.requests.lock().unwrap().len() > 1);
+    }
+}
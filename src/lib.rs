@@ -13,6 +13,7 @@
 //! - Robust error handling with detailed error types
 //! - Custom Time type for API timestamp handling
 //! - Response parsing with path-based value access
+//! - Lazy iteration over paginated list endpoints
 //!
 //! ## Basic Usage
 //!
@@ -72,25 +73,63 @@
 //! ```
 
 pub mod apikey;
+#[cfg(feature = "async")]
+pub mod async_client;
+pub mod cache;
 pub mod client;
+pub mod debug;
+pub mod download;
+pub mod endpoint;
 pub mod error;
+pub mod filter;
+pub mod health;
+pub mod job;
+mod mime;
+pub mod ndjson;
+pub mod paginate;
+#[cfg(feature = "record-replay")]
+pub mod replay;
 pub mod response;
 pub mod rest;
+pub mod retry;
 pub mod time;
 pub mod token;
+pub mod trace;
+pub mod transport;
 pub mod upload;
 
 // Re-export main types for convenience
-pub use apikey::ApiKey;
-pub use client::Config;
-pub use error::{RestError, Result};
-pub use response::{Param, Response};
+pub use apikey::{ApiKey, ParamNames, SignedParams, SigningInput};
+#[cfg(feature = "async")]
+pub use async_client::AsyncClient;
+pub use cache::{Cache, DiskCache};
+pub use client::{Config, TimeoutProfile};
+pub use download::{download_blob, DownloadProgressFn};
+pub use endpoint::{EndpointInfo, ParamInfo};
+pub use error::{AuthErrorKind, RestError, Result};
+pub use filter::{FieldFilter, Filter};
+pub use health::HealthReport;
+pub use job::{Job, JobWaitOptions};
+pub use ndjson::NdjsonStream;
+pub use paginate::{PageIterator, Paging};
+pub use response::{ExpectOk, Param, Response};
 #[allow(deprecated)]
 pub use rest::RestContext;
-pub use rest::{apply, do_request, Client};
-pub use time::Time;
+pub use rest::{
+    apply, do_request, validate_path, version, AuthMethod, Client, QueryEncoding, RequestOptions,
+    PROTOCOL_VERSION,
+};
+pub use retry::{RetryEvent, RetryHookFn, RetryOutcome, RetryPolicy};
+pub use time::{Time, ZonedTime};
 pub use token::Token;
-pub use upload::{upload, UploadInfo, UploadProgressFn};
+pub use trace::RequestTrace;
+pub use transport::{HttpRequest, HttpResponse, StreamingResponse, Transport};
+pub use upload::{
+    resume_from_file, upload, upload_bytes, upload_file, upload_multi, upload_streaming,
+    upload_with_options, AwsCredentials, BucketEndpoint, ProgressAggregator,
+    UploadDetailedProgressFn, UploadInfo, UploadMethod, UploadOptions, UploadPlan,
+    UploadPrepareResponse, UploadProgress, UploadProgressFn,
+};
 
 // Re-export serde_json for convenience
 pub use serde_json::json;
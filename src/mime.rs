@@ -0,0 +1,167 @@
+//! MIME type detection for file uploads.
+//!
+//! Used by [`upload_file`](crate::upload_file) when no MIME type is given:
+//! the caller's extension overrides come first, then the built-in extension
+//! table, then sniffing the first bytes of the content, and finally
+//! `application/octet-stream`.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Type of content nothing else identified.
+pub(crate) const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+/// Bytes of content [`sniff`] looks at.
+pub(crate) const SNIFF_LEN: usize = 512;
+
+/// Built-in extension table, lowercase extensions.
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("7z", "application/x-7z-compressed"),
+    ("avif", "image/avif"),
+    ("bmp", "image/bmp"),
+    ("css", "text/css"),
+    ("csv", "text/csv"),
+    ("doc", "application/msword"),
+    (
+        "docx",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    ),
+    ("flac", "audio/flac"),
+    ("gif", "image/gif"),
+    ("gz", "application/gzip"),
+    ("heic", "image/heic"),
+    ("htm", "text/html"),
+    ("html", "text/html"),
+    ("ico", "image/vnd.microsoft.icon"),
+    ("jpeg", "image/jpeg"),
+    ("jpg", "image/jpeg"),
+    ("js", "text/javascript"),
+    ("json", "application/json"),
+    ("m4a", "audio/mp4"),
+    ("md", "text/markdown"),
+    ("mkv", "video/x-matroska"),
+    ("mov", "video/quicktime"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+    ("ogg", "audio/ogg"),
+    ("pdf", "application/pdf"),
+    ("png", "image/png"),
+    ("svg", "image/svg+xml"),
+    ("tar", "application/x-tar"),
+    ("tif", "image/tiff"),
+    ("tiff", "image/tiff"),
+    ("txt", "text/plain"),
+    ("wasm", "application/wasm"),
+    ("wav", "audio/wav"),
+    ("webm", "video/webm"),
+    ("webp", "image/webp"),
+    ("xls", "application/vnd.ms-excel"),
+    (
+        "xlsx",
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    ),
+    ("xml", "application/xml"),
+    ("zip", "application/zip"),
+];
+
+/// Leading bytes of common formats.
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"ID3", "audio/mpeg"),
+    (b"OggS", "audio/ogg"),
+    (b"fLaC", "audio/flac"),
+    (b"\x1a\x45\xdf\xa3", "video/webm"),
+    (b"\0asm", "application/wasm"),
+];
+
+/// MIME type of the file at `path` whose content starts with `head`.
+///
+/// `overrides` maps extensions (without the dot, any case) to MIME types
+/// and wins over everything else.
+pub(crate) fn detect(path: &Path, head: &[u8], overrides: &HashMap<String, String>) -> String {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+
+    if let Some(ref ext) = extension {
+        let custom = overrides
+            .iter()
+            .find(|(k, _)| k.trim_start_matches('.').eq_ignore_ascii_case(ext));
+        if let Some((_, mime)) = custom {
+            return mime.clone();
+        }
+        if let Some((_, mime)) = EXTENSIONS.iter().find(|(e, _)| e == ext) {
+            return mime.to_string();
+        }
+    }
+
+    sniff(head).unwrap_or(DEFAULT_MIME_TYPE).to_string()
+}
+
+/// MIME type recognized from the first bytes of some content.
+fn sniff(head: &[u8]) -> Option<&'static str> {
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(sig, _)| head.starts_with(sig)) {
+        return Some(mime);
+    }
+    match head {
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Some("audio/wav"),
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some("video/mp4"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_precedence() {
+        let mut overrides = HashMap::new();
+        overrides.insert(".KLB".to_string(), "application/x-klb".to_string());
+        overrides.insert("png".to_string(), "image/x-custom".to_string());
+        let png = b"\x89PNG\r\n\x1a\n....";
+
+        // User map, then built-in table, then sniffing, then the default.
+        assert_eq!(
+            detect(Path::new("a.klb"), b"", &overrides),
+            "application/x-klb"
+        );
+        assert_eq!(
+            detect(Path::new("a.PNG"), b"", &overrides),
+            "image/x-custom"
+        );
+        assert_eq!(
+            detect(Path::new("a.png"), b"", &HashMap::new()),
+            "image/png"
+        );
+        assert_eq!(
+            detect(Path::new("a.JPG"), png, &HashMap::new()),
+            "image/jpeg"
+        );
+        assert_eq!(
+            detect(Path::new("upload"), png, &HashMap::new()),
+            "image/png"
+        );
+        assert_eq!(
+            detect(Path::new("a.bin"), b"??", &HashMap::new()),
+            DEFAULT_MIME_TYPE
+        );
+    }
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(sniff(b"%PDF-1.7"), Some("application/pdf"));
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff(b"\0\0\0\x20ftypisom"), Some("video/mp4"));
+        assert_eq!(sniff(b"RIFF"), None);
+        assert_eq!(sniff(b""), None);
+    }
+}
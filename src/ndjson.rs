@@ -0,0 +1,102 @@
+//! Streaming of newline-delimited JSON (NDJSON) responses.
+//!
+//! Some endpoints answer large result sets with one JSON value per line
+//! rather than a single enveloped response. [`Client::stream_ndjson`] reads
+//! such a body as it arrives and yields the values one by one, so the whole
+//! result never has to be held in memory.
+//!
+//! [`Client::stream_ndjson`]: crate::Client::stream_ndjson
+
+use crate::error::{RestError, Result};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read};
+
+/// Iterator over the values of an NDJSON response body
+///
+/// Created by [`Client::stream_ndjson`]. Blank lines are skipped. A line that
+/// is not valid JSON, or a failed read, is yielded as an `Err` and ends the
+/// iteration.
+///
+/// [`Client::stream_ndjson`]: crate::Client::stream_ndjson
+pub struct NdjsonStream {
+    /// Remaining body; `None` once exhausted or after an error
+    reader: Option<BufReader<Box<dyn Read>>>,
+    line: Vec<u8>,
+}
+
+impl NdjsonStream {
+    pub(crate) fn new(body: Box<dyn Read>) -> Self {
+        NdjsonStream {
+            reader: Some(BufReader::new(body)),
+            line: Vec::new(),
+        }
+    }
+
+    /// Read the next non-blank line into `self.line`; `false` at the end.
+    fn read_line(&mut self) -> Result<bool> {
+        let Some(reader) = self.reader.as_mut() else {
+            return Ok(false);
+        };
+        loop {
+            self.line.clear();
+            if reader.read_until(b'\n', &mut self.line)? == 0 {
+                return Ok(false);
+            }
+            if !self.line.trim_ascii().is_empty() {
+                return Ok(true);
+            }
+        }
+    }
+}
+
+impl Iterator for NdjsonStream {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = match self.read_line() {
+            Ok(false) => None,
+            Ok(true) => Some(serde_json::from_slice(&self.line).map_err(RestError::from)),
+            Err(e) => Some(Err(e)),
+        };
+        if !matches!(item, Some(Ok(_))) {
+            self.reader = None;
+        }
+        item
+    }
+}
+
+impl std::fmt::Debug for NdjsonStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NdjsonStream")
+            .field("done", &self.reader.is_none())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Cursor;
+
+    fn stream(body: &'static [u8]) -> NdjsonStream {
+        NdjsonStream::new(Box::new(Cursor::new(body)))
+    }
+
+    #[test]
+    fn test_ndjson_values() {
+        let values: Vec<Value> = stream(b"{\"id\":1}\n\n  \r\n[2]\r\n\"three\"")
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(values, [json!({"id": 1}), json!([2]), json!("three")]);
+        assert_eq!(stream(b"").count(), 0);
+    }
+
+    #[test]
+    fn test_ndjson_stops_after_error() {
+        let mut values = stream(b"1\n{oops\n3\n");
+        assert_eq!(values.next().unwrap().unwrap(), json!(1));
+        assert!(matches!(values.next(), Some(Err(RestError::Json(_)))));
+        assert!(values.next().is_none());
+    }
+}
@@ -0,0 +1,405 @@
+//! Iteration over paginated list endpoints.
+//!
+//! List endpoints return one page of results in `data` and describe the
+//! remaining pages in the response's `paging` object, in one of two styles:
+//!
+//! - page numbers: `paging` holds `page_no` and `page_max`; the next page is
+//!   requested with `page_no + 1` until `page_max` is reached
+//! - cursors: `paging` holds an opaque `next` (or `cursor`) token; it is sent
+//!   back as the `cursor` parameter until no token is returned
+//!
+//! The style is detected from each response, so callers do not need to know
+//! which one an endpoint uses. See [`Client::paginate`]. Field names vary
+//! between endpoints (`page` for `page_no`, `total` for `count`, ...);
+//! [`Paging`] accepts the known variants.
+
+use crate::error::{RestError, Result};
+use crate::response::{take_field, Param};
+use crate::rest::Client;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+/// Request parameter carrying the page number.
+const PAGE_PARAM: &str = "page_no";
+/// Request parameter carrying the cursor token.
+const CURSOR_PARAM: &str = "cursor";
+
+/// Names of the current page number, first one preferred.
+const PAGE_NO_KEYS: [&str; 2] = ["page_no", "page"];
+/// Names of the last page number.
+const PAGE_MAX_KEYS: [&str; 3] = ["page_max", "pages", "page_count"];
+/// Names of the total item count.
+const COUNT_KEYS: [&str; 3] = ["count", "total", "total_count"];
+/// Names of the page size.
+const PER_PAGE_KEYS: [&str; 2] = ["results_per_page", "per_page"];
+/// Names of the next page's cursor token.
+const NEXT_KEYS: [&str; 3] = ["next", "cursor", "next_cursor"];
+
+/// The `paging` object of a list response
+///
+/// Endpoints name these fields inconsistently, so each accepts several
+/// names: `page_no` (or `page`), `page_max` (`pages`, `page_count`), `count`
+/// (`total`, `total_count`), `results_per_page` (`per_page`) and `next`
+/// (`cursor`, `next_cursor`). Numbers may be sent as strings. Other fields
+/// are kept in `extra`. See [`Response::paging`](crate::Response::paging).
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(from = "Map<String, Value>")]
+#[non_exhaustive]
+pub struct Paging {
+    /// Number of this page, starting at 1
+    pub page_no: Option<u64>,
+    /// Number of the last page
+    pub page_max: Option<u64>,
+    /// Total number of items across all pages
+    pub count: Option<u64>,
+    /// Number of items per page
+    pub results_per_page: Option<u64>,
+    /// Cursor token of the next page, for cursor-paged endpoints
+    pub next: Option<String>,
+    /// Any other fields
+    pub extra: Map<String, Value>,
+}
+
+impl From<Map<String, Value>> for Paging {
+    fn from(mut map: Map<String, Value>) -> Self {
+        let mut take = |keys: &[&str]| take_field(&mut map, keys);
+
+        Paging {
+            page_no: take(&PAGE_NO_KEYS).as_ref().and_then(as_u64),
+            page_max: take(&PAGE_MAX_KEYS).as_ref().and_then(as_u64),
+            count: take(&COUNT_KEYS).as_ref().and_then(as_u64),
+            results_per_page: take(&PER_PAGE_KEYS).as_ref().and_then(as_u64),
+            next: take(&NEXT_KEYS)
+                .as_ref()
+                .and_then(Value::as_str)
+                .filter(|s| !s.is_empty())
+                .map(String::from),
+            extra: map,
+        }
+    }
+}
+
+impl Paging {
+    /// Number of the page after this one, for page-numbered endpoints
+    ///
+    /// `None` on the last page, and when `page_no` or `page_max` is missing.
+    pub fn next_page_number(&self) -> Option<u64> {
+        match (self.page_no, self.page_max) {
+            (Some(page_no), Some(page_max)) if page_no < page_max => Some(page_no + 1),
+            _ => None,
+        }
+    }
+
+    /// Whether another page follows, by page number or cursor token
+    pub fn has_next_page(&self) -> bool {
+        match (self.page_no, self.page_max) {
+            (Some(_), Some(_)) => self.next_page_number().is_some(),
+            _ => self.next.is_some(),
+        }
+    }
+}
+
+/// Where the next page comes from, as read from a response's `paging`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum NextPage {
+    /// Request this page number
+    Page(u64),
+    /// Request the page after this cursor token
+    Cursor(String),
+    /// No more pages
+    Done,
+}
+
+impl NextPage {
+    /// Detect the paging style of `paging` and the page following it.
+    pub(crate) fn from_paging(paging: Option<&Value>) -> Self {
+        let Some(paging) = paging.and_then(Value::as_object).cloned().map(Paging::from) else {
+            return NextPage::Done;
+        };
+
+        if !paging.has_next_page() {
+            return NextPage::Done;
+        }
+        match (paging.next_page_number(), paging.next) {
+            (Some(page), _) => NextPage::Page(page),
+            (None, Some(cursor)) => NextPage::Cursor(cursor),
+            (None, None) => NextPage::Done,
+        }
+    }
+}
+
+impl NextPage {
+    /// Request parameters for this page, derived from the `current` ones, or
+    /// `None` when there are no more pages.
+    pub(crate) fn params(&self, current: &Param) -> Option<Param> {
+        let mut params = current.clone();
+        match self {
+            NextPage::Page(page) => {
+                params.remove(CURSOR_PARAM);
+                params.insert(PAGE_PARAM.to_string(), Value::from(*page));
+            }
+            NextPage::Cursor(cursor) => {
+                params.remove(PAGE_PARAM);
+                params.insert(CURSOR_PARAM.to_string(), Value::from(cursor.as_str()));
+            }
+            NextPage::Done => return None,
+        }
+        Some(params)
+    }
+}
+
+/// Read a page counter that may be sent as a number or a numeric string.
+fn as_u64(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Iterator over every item of a paginated list endpoint.
+///
+/// Created by [`Client::paginate`] or [`Client::paginate_with_method`]. Pages
+/// are fetched lazily as the buffered items run out. A failed request is
+/// yielded as an `Err` and ends the iteration.
+pub struct PageIterator<'a, T> {
+    client: &'a Client,
+    path: String,
+    method: String,
+    /// Items of the current page not yet yielded
+    buffer: VecDeque<T>,
+    /// Parameters of the next page to fetch; `None` once the last page was
+    /// fetched
+    pending: Option<Param>,
+    _item: PhantomData<fn() -> T>,
+}
+
+impl<'a, T: DeserializeOwned> PageIterator<'a, T> {
+    pub(crate) fn new(client: &'a Client, path: &str, method: &str, params: Value) -> Result<Self> {
+        let params = match params {
+            Value::Object(map) => map.into_iter().collect(),
+            Value::Null => Param::new(),
+            _ => {
+                return Err(RestError::RequestBuild(
+                    "pagination parameters must be an object".to_string(),
+                ))
+            }
+        };
+
+        Ok(PageIterator {
+            client,
+            path: path.to_string(),
+            method: method.to_string(),
+            buffer: VecDeque::new(),
+            pending: Some(params),
+            _item: PhantomData,
+        })
+    }
+
+    /// Fetch the pending page into the buffer.
+    fn fetch(&mut self, params: Param) -> Result<()> {
+        let response = self.client.do_request(&self.path, &self.method, &params)?;
+        self.pending = response.paging_next_params(&params);
+
+        let items: Vec<T> = response.take_apply()?;
+        self.buffer.extend(items);
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned> Iterator for PageIterator<'_, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Loop so that empty pages in the middle of a listing are skipped.
+        while self.buffer.is_empty() {
+            let params = self.pending.take()?;
+            if let Err(e) = self.fetch(params) {
+                return Some(Err(e));
+            }
+        }
+        self.buffer.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{HttpRequest, HttpResponse, Transport};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_next_page_numbers() {
+        let paging = json!({"page_no": 1, "page_max": 3, "count": 50});
+        assert_eq!(NextPage::from_paging(Some(&paging)), NextPage::Page(2));
+
+        let paging = json!({"page_no": "3", "page_max": "3"});
+        assert_eq!(NextPage::from_paging(Some(&paging)), NextPage::Done);
+    }
+
+    #[test]
+    fn test_next_page_cursor() {
+        let paging = json!({"next": "abc"});
+        assert_eq!(
+            NextPage::from_paging(Some(&paging)),
+            NextPage::Cursor("abc".to_string())
+        );
+
+        let paging = json!({"cursor": "def"});
+        assert_eq!(
+            NextPage::from_paging(Some(&paging)),
+            NextPage::Cursor("def".to_string())
+        );
+
+        for paging in [json!({"next": ""}), json!({"next": null}), json!({})] {
+            assert_eq!(NextPage::from_paging(Some(&paging)), NextPage::Done);
+        }
+        assert_eq!(NextPage::from_paging(None), NextPage::Done);
+    }
+
+    #[test]
+    fn test_paging_shapes() {
+        let parse = |v: Value| serde_json::from_value::<Paging>(v).unwrap();
+
+        let paging = parse(json!({
+            "page_no": 2, "page_max": 5, "count": 120, "results_per_page": 25
+        }));
+        assert_eq!(
+            (
+                paging.page_no,
+                paging.page_max,
+                paging.count,
+                paging.results_per_page
+            ),
+            (Some(2), Some(5), Some(120), Some(25))
+        );
+
+        let paging = parse(json!({"page": "2", "pages": "5", "total": "120", "per_page": 25}));
+        assert_eq!(
+            (
+                paging.page_no,
+                paging.page_max,
+                paging.count,
+                paging.results_per_page
+            ),
+            (Some(2), Some(5), Some(120), Some(25))
+        );
+
+        let paging = parse(json!({"page_count": 3, "total_count": 7, "next_cursor": "abc"}));
+        assert_eq!((paging.page_max, paging.count), (Some(3), Some(7)));
+        assert_eq!(paging.next.as_deref(), Some("abc"));
+
+        // The preferred name wins when several are sent; unknown fields stay.
+        let paging = parse(json!({"next": null, "cursor": "def", "page": 1, "page_no": 4, "x": 1}));
+        assert_eq!(paging.next.as_deref(), Some("def"));
+        assert_eq!(paging.page_no, Some(4));
+        assert_eq!(paging.extra, *json!({"x": 1}).as_object().unwrap());
+
+        // Helpers for driving pagination by hand.
+        let paging = parse(json!({"page_no": 2, "page_max": 3}));
+        assert_eq!(paging.next_page_number(), Some(3));
+        assert!(paging.has_next_page());
+        let paging = parse(json!({"page_no": 3, "page_max": 3, "next": "ignored"}));
+        assert_eq!(paging.next_page_number(), None);
+        assert!(!paging.has_next_page());
+        let paging = parse(json!({"next": "abc"}));
+        assert_eq!(paging.next_page_number(), None);
+        assert!(paging.has_next_page());
+        assert!(!Paging::default().has_next_page());
+
+        // Alias names drive pagination too.
+        assert_eq!(
+            NextPage::from_paging(Some(&json!({"page": 1, "pages": 2}))),
+            NextPage::Page(2)
+        );
+    }
+
+    #[test]
+    fn test_next_page_params() {
+        let mut current = Param::new();
+        current.insert("q".to_string(), json!("x"));
+        current.insert(CURSOR_PARAM.to_string(), json!("old"));
+
+        let params = NextPage::Page(2).params(&current).unwrap();
+        assert_eq!(params["page_no"], 2);
+        assert_eq!(params["q"], "x");
+        assert!(!params.contains_key(CURSOR_PARAM));
+
+        let params = NextPage::Cursor("abc".to_string()).params(&params).unwrap();
+        assert_eq!(params[CURSOR_PARAM], "abc");
+        assert!(!params.contains_key(PAGE_PARAM));
+
+        assert_eq!(NextPage::Done.params(&current), None);
+    }
+
+    /// Serves three cursor-paged pages, the middle one empty.
+    struct CursorPages;
+
+    impl Transport for CursorPages {
+        fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+            let body = if request.url.contains("p2") {
+                r#"{"result":"success","data":[],"paging":{"next":"p3"}}"#
+            } else if request.url.contains("p3") {
+                r#"{"result":"success","data":[3],"paging":{}}"#
+            } else {
+                r#"{"result":"success","data":[1,2],"paging":{"next":"p2"}}"#
+            };
+            Ok(HttpResponse::new(200, Vec::new(), body.as_bytes().to_vec()))
+        }
+    }
+
+    #[test]
+    fn test_paginate_cursor() {
+        let client = Client::new().with_transport(Arc::new(CursorPages));
+        let items: Vec<i64> = client
+            .paginate("Thing", json!({}))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(items, [1, 2, 3]);
+    }
+
+    /// Serves two page-numbered pages from the `POST` body parameters.
+    struct PostPages;
+
+    impl Transport for PostPages {
+        fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+            assert_eq!(request.method, "POST");
+            let params: Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(params["filter"], "x");
+            let body = match params.get("page_no").and_then(Value::as_u64) {
+                None | Some(1) => {
+                    r#"{"result":"success","data":[1],"paging":{"page_no":1,"page_max":2}}"#
+                }
+                _ => r#"{"result":"success","data":[2],"paging":{"page_no":2,"page_max":2}}"#,
+            };
+            Ok(HttpResponse::new(200, Vec::new(), body.as_bytes().to_vec()))
+        }
+    }
+
+    #[test]
+    fn test_paginate_with_method() {
+        let client = Client::new().with_transport(Arc::new(PostPages));
+        let items: Vec<i64> = client
+            .paginate_with_method("Thing:search", "POST", json!({"filter": "x"}))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(items, [1, 2]);
+    }
+
+    #[test]
+    fn test_params_must_be_object() {
+        let client = Client::new();
+        assert!(PageIterator::<Value>::new(&client, "User", "GET", json!({"a": 1})).is_ok());
+        assert!(PageIterator::<Value>::new(&client, "User", "GET", Value::Null).is_ok());
+        assert!(matches!(
+            PageIterator::<Value>::new(&client, "User", "GET", json!([1])),
+            Err(RestError::RequestBuild(_))
+        ));
+    }
+}
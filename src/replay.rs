@@ -0,0 +1,333 @@
+//! Recording and replaying HTTP interactions, for offline tests.
+//!
+//! Available with the `record-replay` feature. Run against a live backend
+//! once with a [`RecordingTransport`] to save every exchange to a JSON file,
+//! then use a [`ReplayTransport`] on that file to serve the same responses
+//! without network access:
+//!
+//! ```no_run
+//! use klbfw::replay::{RecordingTransport, ReplayTransport};
+//! use klbfw::Client;
+//! use std::sync::Arc;
+//!
+//! # fn main() -> klbfw::Result<()> {
+//! // Record
+//! let ctx = Client::new().with_transport(Arc::new(RecordingTransport::new("fixtures.json")));
+//! let _: String = ctx.apply("Misc/Debug:fixedString", "GET", serde_json::json!({}))?;
+//!
+//! // Replay
+//! let ctx = Client::new().with_transport(Arc::new(ReplayTransport::from_file("fixtures.json")?));
+//! let _: String = ctx.apply("Misc/Debug:fixedString", "GET", serde_json::json!({}))?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Interactions are matched on method, URL and request body. The API key
+//! signing parameters (`_time`, `_nonce`, `_sign`, or the
+//! [custom names](crate::ApiKey::with_param_names) the client's key uses)
+//! change on every request and are left out of the match. Everything else,
+//! including headers, is ignored. Bodies are stored as text, so this is meant for REST calls, not
+//! binary uploads.
+
+use crate::apikey::ParamNames;
+use crate::error::{RestError, Result};
+use crate::transport::{HttpRequest, HttpResponse, RsurlTransport, Transport};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// One recorded request/response exchange, as stored in the file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    method: String,
+    url: String,
+    body: String,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Interaction {
+    fn matches(&self, method: &str, url: &str, body: &str) -> bool {
+        self.method == method && self.url == url && self.body == body
+    }
+}
+
+/// `url` with the signing parameters that change on every request (as named
+/// by `names`) removed and the query sorted.
+fn normalize_url(url: &str, names: &ParamNames) -> String {
+    let Some((base, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let volatile = names.volatile();
+    let mut pairs: Vec<(String, String)> = form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .filter(|(k, _)| !volatile.contains(&k.as_str()))
+        .collect();
+    if pairs.is_empty() {
+        return base.to_string();
+    }
+    pairs.sort();
+    let query = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(pairs)
+        .finish();
+    format!("{}?{}", base, query)
+}
+
+/// Interactions recorded by this process to `path`, shared by every
+/// [`RecordingTransport`] writing there.
+fn recording(path: &Path) -> Arc<Mutex<Vec<Interaction>>> {
+    type Recordings = Mutex<HashMap<PathBuf, Arc<Mutex<Vec<Interaction>>>>>;
+    static RECORDINGS: OnceLock<Recordings> = OnceLock::new();
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut recordings = RECORDINGS.get_or_init(Default::default).lock().unwrap();
+    Arc::clone(recordings.entry(path).or_default())
+}
+
+/// Transport that forwards requests and saves every exchange to a file
+///
+/// The file is rewritten after each request, so it is complete even if the
+/// process stops early. Recorders on the same path share one recording, so
+/// tests running in parallel, each with a recorder of its own, all end up in
+/// the file.
+pub struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    path: PathBuf,
+    interactions: Arc<Mutex<Vec<Interaction>>>,
+}
+
+impl RecordingTransport {
+    /// Record real network exchanges to `path`
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self::with_inner(path, Arc::new(RsurlTransport))
+    }
+
+    /// Record the exchanges of another transport to `path`
+    pub fn with_inner(path: impl AsRef<Path>, inner: Arc<dyn Transport>) -> Self {
+        RecordingTransport {
+            inner,
+            path: path.as_ref().to_path_buf(),
+            interactions: recording(path.as_ref()),
+        }
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let method = request.method.clone();
+        let url = normalize_url(&request.url, &request.param_names);
+        let body = String::from_utf8_lossy(&request.body).into_owned();
+
+        let response = self.inner.send(request)?;
+
+        let mut interactions = self.interactions.lock().unwrap();
+        interactions.push(Interaction {
+            method,
+            url,
+            body,
+            response: RecordedResponse {
+                status: response.status,
+                headers: response.headers.clone(),
+                body: String::from_utf8_lossy(&response.body).into_owned(),
+            },
+        });
+        std::fs::write(&self.path, serde_json::to_vec_pretty(&*interactions)?)?;
+
+        Ok(response)
+    }
+}
+
+/// Transport that answers requests from a file saved by [`RecordingTransport`]
+///
+/// When a request was recorded several times, the responses are served in
+/// recorded order and the last one repeats. A request with no recording
+/// fails with an error naming it.
+pub struct ReplayTransport {
+    /// Recorded interactions, each with whether it was served already
+    interactions: Mutex<Vec<(Interaction, bool)>>,
+}
+
+impl ReplayTransport {
+    /// Load the interactions recorded in `path`
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        let interactions: Vec<Interaction> = serde_json::from_slice(&data)?;
+        Ok(ReplayTransport {
+            interactions: Mutex::new(interactions.into_iter().map(|i| (i, false)).collect()),
+        })
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let url = normalize_url(&request.url, &request.param_names);
+        let body = String::from_utf8_lossy(&request.body);
+
+        let mut interactions = self.interactions.lock().unwrap();
+        let mut candidates = interactions
+            .iter_mut()
+            .filter(|(i, _)| i.matches(&request.method, &url, &body))
+            .peekable();
+        let mut chosen = None;
+        while let Some(entry) = candidates.next() {
+            if !entry.1 || candidates.peek().is_none() {
+                chosen = Some(entry);
+                break;
+            }
+        }
+
+        let (interaction, served) = chosen.ok_or_else(|| {
+            RestError::Other(format!(
+                "no recorded interaction for {} {}",
+                request.method, url
+            ))
+        })?;
+        *served = true;
+
+        let recorded = &interaction.response;
+        Ok(HttpResponse::new(
+            recorded.status,
+            recorded.headers.clone(),
+            recorded.body.clone().into_bytes(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rest::Client;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Answers every request with a numbered `fixedString`-like response.
+    struct Counter(AtomicUsize);
+
+    impl Transport for Counter {
+        fn send(&self, _request: HttpRequest) -> Result<HttpResponse> {
+            let n = self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(HttpResponse::new(
+                200,
+                vec![("X-Request-Id".to_string(), format!("req-{}", n))],
+                format!(r#"{{"result":"success","data":"call {}"}}"#, n).into_bytes(),
+            ))
+        }
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        let names = ParamNames::default();
+        assert_eq!(
+            normalize_url(
+                "https://h/p?_sign=x&b=2&_time=1&a=1&_nonce=n&_key=k",
+                &names
+            ),
+            "https://h/p?_key=k&a=1&b=2"
+        );
+        assert_eq!(normalize_url("https://h/p?_time=1", &names), "https://h/p");
+        assert_eq!(normalize_url("https://h/p", &names), "https://h/p");
+
+        let renamed = ParamNames {
+            sign: "s".to_string(),
+            nonce: "n".to_string(),
+            ..ParamNames::default()
+        };
+        assert_eq!(
+            normalize_url("https://h/p?s=x&n=1&_time=2&a=1", &renamed),
+            "https://h/p?a=1"
+        );
+    }
+
+    #[test]
+    fn test_record_then_replay() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let recorder = RecordingTransport::with_inner(file.path(), Arc::new(Counter(0.into())));
+        let ctx = Client::new().with_transport(Arc::new(recorder));
+
+        let first: String = ctx.apply("Misc/Debug:fixedString", "GET", ()).unwrap();
+        let second: String = ctx.apply("Misc/Debug:fixedString", "GET", ()).unwrap();
+        let posted: String = ctx
+            .apply("Misc/Debug:argString", "POST", serde_json::json!({"a": 1}))
+            .unwrap();
+        assert_eq!((first.as_str(), second.as_str()), ("call 0", "call 1"));
+
+        let replay = ReplayTransport::from_file(file.path()).unwrap();
+        let ctx = Client::new().with_transport(Arc::new(replay));
+
+        let response = ctx.do_request("Misc/Debug:fixedString", "GET", ()).unwrap();
+        assert_eq!(response.request_id.as_deref(), Some("req-0"));
+        let again: String = ctx.apply("Misc/Debug:fixedString", "GET", ()).unwrap();
+        assert_eq!(again, second);
+        let repeated: String = ctx.apply("Misc/Debug:fixedString", "GET", ()).unwrap();
+        assert_eq!(repeated, second);
+        let replayed: String = ctx
+            .apply("Misc/Debug:argString", "POST", serde_json::json!({"a": 1}))
+            .unwrap();
+        assert_eq!(replayed, posted);
+
+        let missing = ctx.do_request("Misc/Debug:argString", "POST", serde_json::json!({"a": 2}));
+        assert!(matches!(missing, Err(RestError::Other(_))));
+    }
+
+    #[test]
+    fn test_recorders_share_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let recorder = || {
+            let recorder = RecordingTransport::with_inner(file.path(), Arc::new(Counter(0.into())));
+            Client::new().with_transport(Arc::new(recorder))
+        };
+        let (first, second) = (recorder(), recorder());
+        first
+            .do_request("Misc/Debug:fixedString", "GET", ())
+            .unwrap();
+        second
+            .do_request("Misc/Debug:fixedArray", "GET", ())
+            .unwrap();
+        first.do_request("Misc/Debug:argString", "GET", ()).unwrap();
+
+        let saved: Vec<Interaction> =
+            serde_json::from_slice(&std::fs::read(file.path()).unwrap()).unwrap();
+        let urls: Vec<&str> = saved.iter().map(|i| i.url.as_str()).collect();
+        assert_eq!(urls.len(), 3);
+        assert!(urls[1].contains("fixedArray"), "{:?}", urls);
+    }
+
+    #[test]
+    fn test_renamed_signing_params() {
+        let names = ParamNames {
+            time: "t".to_string(),
+            nonce: "n".to_string(),
+            sign: "s".to_string(),
+            ..ParamNames::default()
+        };
+        let key = crate::ApiKey::new("key".to_string(), &"A".repeat(43))
+            .unwrap()
+            .with_param_names(names);
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let recorder = RecordingTransport::with_inner(file.path(), Arc::new(Counter(0.into())));
+        let ctx = Client::new()
+            .with_api_key(key.clone())
+            .with_transport(Arc::new(recorder));
+        ctx.do_request("Misc/Debug:fixedString", "GET", ()).unwrap();
+
+        // The client's names are left out, so a new signature still matches.
+        let saved: Vec<Interaction> =
+            serde_json::from_slice(&std::fs::read(file.path()).unwrap()).unwrap();
+        assert!(
+            saved[0].url.ends_with("?_=null&_key=key"),
+            "{}",
+            saved[0].url
+        );
+        let replay = ReplayTransport::from_file(file.path()).unwrap();
+        let ctx = Client::new()
+            .with_api_key(key)
+            .with_transport(Arc::new(replay));
+        let replayed: String = ctx.apply("Misc/Debug:fixedString", "GET", ()).unwrap();
+        assert_eq!(replayed, "call 0");
+    }
+}
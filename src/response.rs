@@ -1,4 +1,8 @@
-use serde::{Deserialize, Serialize};
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{
+    DeserializeSeed, Deserializer, EnumAccess, IntoDeserializer, VariantAccess, Visitor,
+};
+use serde::{forward_to_deserialize_any, Deserialize, Serialize};
 use serde_json::Value;
 
 /// Param is a convenience type for parameters passed to REST API requests.
@@ -63,14 +67,97 @@ pub struct Response {
     /// X-Request-Id header from HTTP response (not serialized)
     #[serde(skip)]
     pub request_id: Option<String>,
+
+    /// HTTP status code of the response (not serialized), `0` when the
+    /// response did not come from an HTTP exchange
+    #[serde(skip)]
+    pub status: u16,
+
+    /// Whether this response was served from a local cache instead of the
+    /// network (not serialized). Always `false` unless a caching layer is in
+    /// use.
+    #[serde(skip)]
+    pub from_cache: bool,
+
+    /// Hex-encoded SHA-256 of the uploaded content, on the response
+    /// completing an upload (not serialized), see [`UploadInfo::sha256`]
+    ///
+    /// [`UploadInfo::sha256`]: crate::upload::UploadInfo::sha256
+    #[serde(skip)]
+    pub upload_sha256: Option<String>,
 }
 
 impl Response {
+    /// Wrap the body of a raw endpoint in a synthetic success response
+    ///
+    /// Raw endpoints return their data without the standard envelope; the
+    /// result carries it as `data` so it can be handled like any other
+    /// response.
+    pub fn from_raw(data: Value) -> Self {
+        Response {
+            result: "success".to_string(),
+            data: Some(data),
+            error: None,
+            code: None,
+            extra: None,
+            token: None,
+            paging: None,
+            job: None,
+            time: None,
+            access: None,
+            exception: None,
+            redirect_url: None,
+            redirect_code: None,
+            request_id: None,
+            status: 0,
+            from_cache: false,
+            upload_sha256: None,
+        }
+    }
+
+    /// Parse a response body, enveloped or raw.
+    ///
+    /// Returns the response and whether the body was raw JSON that had to be
+    /// wrapped with [`from_raw`](Self::from_raw).
+    pub(crate) fn parse_body(body: &[u8]) -> serde_json::Result<(Self, bool)> {
+        Self::from_value(serde_json::from_slice(body)?)
+    }
+
+    /// Read a response from a parsed body, see [`parse_body`](Self::parse_body).
+    pub(crate) fn from_value(value: Value) -> serde_json::Result<(Self, bool)> {
+        let enveloped = matches!(
+            value.get("result").and_then(Value::as_str),
+            Some("success" | "error" | "redirect")
+        );
+        if enveloped {
+            Ok((serde_json::from_value(value)?, false))
+        } else {
+            Ok((Response::from_raw(value), true))
+        }
+    }
+
+    /// The HTTP status code the response came with
+    ///
+    /// Tells apart successes such as `201 Created` and `202 Accepted`.
+    /// Returns `0` for responses that did not come from an HTTP exchange,
+    /// like those built with [`from_raw`](Self::from_raw).
+    pub fn http_status(&self) -> u16 {
+        self.status
+    }
+
     /// Get the raw data value from the response
     pub fn raw(&self) -> Option<&Value> {
         self.data.as_ref()
     }
 
+    /// Consume the response and return its data
+    ///
+    /// Returns [`RestError::EmptyData`](crate::RestError::EmptyData) when the
+    /// response has no data.
+    pub fn into_data(self) -> Result<Value, crate::error::RestError> {
+        self.data.ok_or(crate::error::RestError::EmptyData)
+    }
+
     /// Get the complete response as a map including metadata.
     ///
     /// Fields that are `None` are omitted (see the `skip_serializing_if`
@@ -82,15 +169,68 @@ impl Response {
         }
     }
 
+    /// Consume the response and return the complete envelope as a map
+    ///
+    /// The owned counterpart of [`full_raw`](Self::full_raw): fields are
+    /// moved instead of cloned. `None` fields are omitted the same way, and
+    /// the `X-Request-Id`, when known, is included under `_request_id`.
+    pub fn into_full_map(self) -> serde_json::Map<String, Value> {
+        let mut map = serde_json::Map::new();
+        let mut put = |key: &str, value: Option<Value>| {
+            if let Some(value) = value {
+                map.insert(key.to_string(), value);
+            }
+        };
+
+        put("result", Some(Value::String(self.result)));
+        put("data", self.data);
+        put("error", self.error.map(Value::String));
+        put("code", self.code.map(Value::from));
+        put("extra", self.extra.map(Value::String));
+        put("token", self.token.map(Value::String));
+        put("paging", self.paging);
+        put("job", self.job);
+        put("time", self.time);
+        put("access", self.access);
+        put("exception", self.exception.map(Value::String));
+        put("redirect_url", self.redirect_url.map(Value::String));
+        put("redirect_code", self.redirect_code.map(Value::from));
+        put("_request_id", self.request_id.map(Value::String));
+        map
+    }
+
     /// Apply unmarshals the response data into the provided type
     pub fn apply<T>(&self) -> Result<T, crate::error::RestError>
     where
         T: serde::de::DeserializeOwned,
     {
-        match &self.data {
-            Some(data) => serde_json::from_value(data.clone()).map_err(|e| e.into()),
-            None => serde_json::from_value(Value::Null).map_err(|e| e.into()),
-        }
+        from_data(self.data.clone().unwrap_or(Value::Null)).map_err(|e| e.into())
+    }
+
+    /// Consume the response and unmarshal its data into the provided type
+    ///
+    /// Unlike [`apply`](Self::apply), the data is moved into the deserializer
+    /// instead of cloned, which avoids transiently doubling memory for large
+    /// payloads. Use `apply` when the response is needed afterwards.
+    pub fn take_apply<T>(self) -> Result<T, crate::error::RestError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        from_data(self.data.unwrap_or(Value::Null)).map_err(|e| e.into())
+    }
+
+    /// Like [`apply`](Self::apply), but tolerates empty arrays standing in for
+    /// empty objects
+    ///
+    /// PHP backends serialize an empty associative array as `[]`, which does
+    /// not deserialize into a map or struct. Here an empty array is read as
+    /// an empty object wherever a map or struct is expected; where a
+    /// sequence is expected, it stays an empty sequence.
+    pub fn apply_lenient<T>(&self) -> Result<T, crate::error::RestError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        from_data(Lenient(self.data.clone().unwrap_or(Value::Null))).map_err(|e| e.into())
     }
 
     /// Get a value from the response data by a slash-separated path.
@@ -114,12 +254,204 @@ impl Response {
         Some(current)
     }
 
+    /// Like [`get`](Self::get), but with an error saying why the lookup
+    /// failed
+    ///
+    /// The [`RestError::Other`](crate::RestError::Other) message names the
+    /// path and the step that failed, e.g. `path 'user/name': expected object
+    /// at 'user', found array`.
+    pub fn try_get(&self, path: &str) -> Result<&Value, crate::error::RestError> {
+        let fail =
+            |reason: String| crate::error::RestError::Other(format!("path '{}': {}", path, reason));
+        let mut current = self
+            .data
+            .as_ref()
+            .ok_or_else(|| fail("response has no data".to_string()))?;
+
+        let mut at = String::new();
+        for part in path.split('/').filter(|s| !s.is_empty()) {
+            let location = if at.is_empty() { "data" } else { at.as_str() };
+            current = match current {
+                Value::Object(map) => map
+                    .get(part)
+                    .ok_or_else(|| fail(format!("no field '{}' in '{}'", part, location)))?,
+                Value::Array(arr) => match part.parse::<usize>() {
+                    Ok(index) => arr.get(index).ok_or_else(|| {
+                        fail(format!(
+                            "index {} out of range at '{}' (length {})",
+                            index,
+                            location,
+                            arr.len()
+                        ))
+                    })?,
+                    Err(_) => {
+                        return Err(fail(format!(
+                            "expected object at '{}', found array",
+                            location
+                        )))
+                    }
+                },
+                other => {
+                    return Err(fail(format!(
+                        "expected object at '{}', found {}",
+                        location,
+                        type_name(other)
+                    )))
+                }
+            };
+            if !at.is_empty() {
+                at.push('/');
+            }
+            at.push_str(part);
+        }
+
+        Ok(current)
+    }
+
+    /// Like [`get_string`](Self::get_string), but with an error saying why
+    /// the lookup failed
+    ///
+    /// See [`try_get`](Self::try_get); a value that is not a string is an
+    /// error too.
+    pub fn try_get_string(&self, path: &str) -> Result<String, crate::error::RestError> {
+        match self.try_get(path)? {
+            Value::String(s) => Ok(s.clone()),
+            other => Err(crate::error::RestError::Other(format!(
+                "path '{}': expected string, found {}",
+                path,
+                type_name(other)
+            ))),
+        }
+    }
+
+    /// Get binary data sent as a base64 string, by a slash-separated path
+    ///
+    /// Accepts the standard and the URL-safe alphabets, with or without
+    /// padding. Fails like [`try_get_string`](Self::try_get_string) when
+    /// there is no string at `path`, and with an error naming the path when
+    /// it is not valid base64.
+    pub fn get_bytes(&self, path: &str) -> Result<Vec<u8>, crate::error::RestError> {
+        use base64::engine::general_purpose::{STANDARD_NO_PAD, URL_SAFE_NO_PAD};
+        use base64::Engine;
+
+        let encoded = self.try_get_string(path)?;
+        let trimmed = encoded.trim_end_matches('=');
+        let engine = if trimmed.contains(['-', '_']) {
+            URL_SAFE_NO_PAD
+        } else {
+            STANDARD_NO_PAD
+        };
+        engine.decode(trimmed).map_err(|e| {
+            crate::error::RestError::Other(format!("path '{}': invalid base64: {}", path, e))
+        })
+    }
+
     /// Get a string value from the response data by a slash-separated path
     pub fn get_string(&self, path: &str) -> Option<String> {
         self.get(path)
             .and_then(|v| v.as_str().map(|s| s.to_string()))
     }
 
+    /// Get a signed integer value from the response data by a slash-separated
+    /// path.
+    ///
+    /// Integers are read exactly (serde_json keeps them as 64-bit integers, not
+    /// floats), so IDs above 2^53 do not lose precision. Strings holding a
+    /// decimal integer are accepted too, as servers often send large IDs that
+    /// way.
+    pub fn get_i64(&self, path: &str) -> Option<i64> {
+        match self.get(path)? {
+            Value::Number(n) => n.as_i64(),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Get an unsigned integer value from the response data by a
+    /// slash-separated path.
+    ///
+    /// See [`get_i64`](Self::get_i64) for the precision guarantees and accepted
+    /// forms.
+    pub fn get_u64(&self, path: &str) -> Option<u64> {
+        match self.get(path)? {
+            Value::Number(n) => n.as_u64(),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Get a floating point value from the response data by a
+    /// slash-separated path.
+    ///
+    /// Integers are converted, and strings holding a number are accepted
+    /// like in [`get_i64`](Self::get_i64).
+    pub fn get_f64(&self, path: &str) -> Option<f64> {
+        match self.get(path)? {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Get a boolean value from the response data by a slash-separated path.
+    ///
+    /// Accepts JSON `true` and `false`, and the strings `"true"` and
+    /// `"false"` some endpoints send instead.
+    pub fn get_bool(&self, path: &str) -> Option<bool> {
+        match self.get(path)? {
+            Value::Bool(b) => Some(*b),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The `paging` object of a list response, if any
+    ///
+    /// Accepts the field name variants used across endpoints; see
+    /// [`Paging`](crate::Paging).
+    pub fn paging(&self) -> Option<crate::paginate::Paging> {
+        self.paging
+            .as_ref()
+            .and_then(Value::as_object)
+            .cloned()
+            .map(Into::into)
+    }
+
+    /// The `job` object of a response starting a long-running task, if any
+    ///
+    /// Accepts the field name variants used across endpoints; see
+    /// [`Job`](crate::Job). Follow the job with
+    /// [`Client::wait_for_job`](crate::Client::wait_for_job).
+    pub fn job_info(&self) -> Option<crate::job::Job> {
+        self.job.as_ref().and_then(crate::job::Job::from_value)
+    }
+
+    /// Request parameters for the page after this one, or `None` on the last
+    /// page
+    ///
+    /// `current` holds the parameters this response was requested with. The
+    /// result is a copy with `page_no` incremented or, for cursor-paged
+    /// endpoints, the `cursor` set (see [`paginate`](crate::paginate)). This
+    /// is the paging arithmetic behind [`Client::paginate`](crate::Client::paginate),
+    /// for callers driving the requests themselves.
+    pub fn paging_next_params(&self, current: &Param) -> Option<Param> {
+        crate::paginate::NextPage::from_paging(self.paging.as_ref()).params(current)
+    }
+
+    /// Return the response if it is a success, or panic with `msg` and the
+    /// full [report](crate::RestError::report) of its error
+    ///
+    /// A convenience for examples and command line glue where propagating
+    /// errors is overkill; library code should handle the error instead.
+    #[track_caller]
+    pub fn expect_success(self, msg: &str) -> Response {
+        if self.result == "success" {
+            return self;
+        }
+        let err = crate::error::RestError::from_response(self);
+        panic!("{}:\n{}", msg, err.report())
+    }
+
     /// Get metadata fields with @ prefix
     pub fn offset_get(&self, key: &str) -> Option<Value> {
         if let Some(stripped) = key.strip_prefix('@') {
@@ -141,6 +473,236 @@ impl Response {
     }
 }
 
+/// [`expect`](Result::expect) for request results, with the full error
+/// report in the panic message
+///
+/// Like [`Response::expect_success`], meant for examples and scripts, not
+/// library code.
+///
+/// ```no_run
+/// use klbfw::{Client, ExpectOk};
+///
+/// let response = Client::new()
+///     .do_request("Misc/Debug:serverTime", "GET", ())
+///     .expect_ok("server time");
+/// ```
+pub trait ExpectOk<T> {
+    /// Return the value, or panic with `msg` followed by
+    /// [`RestError::report`](crate::RestError::report)
+    fn expect_ok(self, msg: &str) -> T;
+}
+
+impl<T> ExpectOk<T> for Result<T, crate::error::RestError> {
+    #[track_caller]
+    fn expect_ok(self, msg: &str) -> T {
+        match self {
+            Ok(value) => value,
+            Err(err) => panic!("{}:\n{}", msg, err.report()),
+        }
+    }
+}
+
+/// JSON type of `value`, for error messages.
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Remove every variant of a field named `keys` from `map`, returning the
+/// first non-null one, for objects whose field names vary between endpoints.
+pub(crate) fn take_field(map: &mut serde_json::Map<String, Value>, keys: &[&str]) -> Option<Value> {
+    keys.iter()
+        .filter_map(|k| map.remove(*k).filter(|v| !v.is_null()))
+        .reduce(|first, _| first)
+}
+
+/// Deserializer of a JSON value reading an empty array as an empty object
+/// where a map or struct is expected, for [`Response::apply_lenient`].
+struct Lenient(Value);
+
+impl Lenient {
+    /// Visit the elements of an array, each read leniently.
+    fn visit_array<'de, V>(items: Vec<Value>, visitor: V) -> serde_json::Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let mut seq = SeqDeserializer::new(items.into_iter().map(Lenient));
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    /// Visit the entries of an object, each value read leniently.
+    fn visit_object<'de, V>(
+        map: serde_json::Map<String, Value>,
+        visitor: V,
+    ) -> serde_json::Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let mut map = MapDeserializer::new(map.into_iter().map(|(k, v)| (k, Lenient(v))));
+        let value = visitor.visit_map(&mut map)?;
+        map.end()?;
+        Ok(value)
+    }
+}
+
+impl<'de> IntoDeserializer<'de, serde_json::Error> for Lenient {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for Lenient {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> serde_json::Result<V::Value> {
+        match self.0 {
+            Value::Array(items) => Lenient::visit_array(items, visitor),
+            Value::Object(map) => Lenient::visit_object(map, visitor),
+            other => other.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> serde_json::Result<V::Value> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> serde_json::Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> serde_json::Result<V::Value> {
+        match self.0 {
+            Value::Array(items) if items.is_empty() => {
+                Lenient::visit_object(serde_json::Map::new(), visitor)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> serde_json::Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> serde_json::Result<V::Value> {
+        // Variants with content are single-entry objects; read the content
+        // leniently too.
+        match self.0 {
+            Value::Object(map) if map.len() == 1 => {
+                let (variant, content) = map.into_iter().next().unwrap_or_default();
+                visitor.visit_enum(LenientEnum(variant, Lenient(content)))
+            }
+            other => other.deserialize_enum(name, variants, visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct identifier
+        ignored_any
+    }
+}
+
+/// Variant of an enum read by [`Lenient`], and its content.
+struct LenientEnum(String, Lenient);
+
+impl<'de> EnumAccess<'de> for LenientEnum {
+    type Error = serde_json::Error;
+    type Variant = Lenient;
+
+    fn variant_seed<S>(self, seed: S) -> serde_json::Result<(S::Value, Lenient)>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(self.0.into_deserializer())?;
+        Ok((variant, self.1))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Lenient {
+    type Error = serde_json::Error;
+
+    fn unit_variant(self) -> serde_json::Result<()> {
+        Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<S>(self, seed: S) -> serde_json::Result<S::Value>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> serde_json::Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> serde_json::Result<V::Value> {
+        self.deserialize_struct("", fields, visitor)
+    }
+}
+
+/// Deserialize response data.
+///
+/// With the `json-path` feature, errors are prefixed with the path of the
+/// failing field, such as `data.items[3].name`.
+fn from_data<'de, T, D>(data: D) -> serde_json::Result<T>
+where
+    T: serde::de::DeserializeOwned,
+    D: Deserializer<'de, Error = serde_json::Error>,
+{
+    #[cfg(feature = "json-path")]
+    {
+        serde_path_to_error::deserialize(data).map_err(|e| {
+            let path = e.path().to_string();
+            let err = e.into_inner();
+            if path == "." {
+                err
+            } else {
+                serde::de::Error::custom(format!("data.{}: {}", path, err))
+            }
+        })
+    }
+    #[cfg(not(feature = "json-path"))]
+    {
+        T::deserialize(data)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -155,6 +717,165 @@ mod tests {
         let response: Response = serde_json::from_str(json).unwrap();
         assert_eq!(response.result, "success");
         assert!(response.data.is_some());
+        assert!(!response.from_cache);
+        assert!(!response.full_raw().contains_key("from_cache"));
+    }
+
+    #[test]
+    fn test_response_apply_lenient() {
+        #[derive(Debug, Default, PartialEq, Deserialize)]
+        struct Settings {
+            theme: Option<String>,
+            limits: Option<std::collections::HashMap<String, i64>>,
+        }
+
+        let response: Response =
+            serde_json::from_str(r#"{"result": "success", "data": []}"#).unwrap();
+        assert!(response.apply::<Settings>().is_err());
+        assert_eq!(
+            response.apply_lenient::<Settings>().unwrap(),
+            Settings::default()
+        );
+
+        let response: Response = serde_json::from_str(
+            r#"{"result": "success", "data": {"theme": "dark", "limits": []}}"#,
+        )
+        .unwrap();
+        let settings: Settings = response.apply_lenient().unwrap();
+        assert_eq!(settings.theme.as_deref(), Some("dark"));
+        assert!(settings.limits.unwrap().is_empty());
+
+        // Sequences still deserialize from an empty array.
+        let response: Response =
+            serde_json::from_str(r#"{"result": "success", "data": []}"#).unwrap();
+        assert!(response.apply_lenient::<Vec<i64>>().unwrap().is_empty());
+
+        // Each empty array is read as what its field expects.
+        #[derive(Debug, Deserialize)]
+        struct Tagged {
+            tags: Vec<String>,
+            meta: std::collections::HashMap<String, String>,
+        }
+        let response: Response =
+            serde_json::from_str(r#"{"result": "success", "data": {"tags": [], "meta": []}}"#)
+                .unwrap();
+        assert!(response.apply::<Tagged>().is_err());
+        let tagged: Tagged = response.apply_lenient().unwrap();
+        assert!(tagged.tags.is_empty() && tagged.meta.is_empty());
+
+        // Inside sequences and enum variants too.
+        #[derive(Debug, PartialEq, Deserialize)]
+        enum Shape {
+            Empty,
+            Box(Settings),
+            Pair(Option<Settings>, Vec<i64>),
+        }
+        #[derive(Debug, Deserialize)]
+        struct Mixed {
+            nested: Vec<Settings>,
+            shapes: Vec<Shape>,
+        }
+        let response: Response = serde_json::from_str(
+            r#"{"result": "success", "data": {
+                "nested": [[]],
+                "shapes": ["Empty", {"Box": []}, {"Pair": [[], []]}]
+            }}"#,
+        )
+        .unwrap();
+        let mixed: Mixed = response.apply_lenient().unwrap();
+        assert_eq!(mixed.nested, [Settings::default()]);
+        assert_eq!(
+            mixed.shapes,
+            [
+                Shape::Empty,
+                Shape::Box(Settings::default()),
+                Shape::Pair(Some(Settings::default()), Vec::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_response_into_full_map() {
+        let mut response: Response = serde_json::from_str(
+            r#"{"result": "error", "error": "nope", "code": 404, "data": {"id": 1},
+                "paging": {"page_no": 1}, "redirect_code": 302}"#,
+        )
+        .unwrap();
+        assert_eq!(response.clone().into_full_map(), response.full_raw());
+
+        response.request_id = Some("req-1".to_string());
+        let mut expected = response.full_raw();
+        expected.insert("_request_id".to_string(), Value::from("req-1"));
+        assert_eq!(response.into_full_map(), expected);
+    }
+
+    #[test]
+    fn test_response_paging() {
+        let response: Response = serde_json::from_str(
+            r#"{"result": "success", "data": [], "paging": {"page": 1, "pages": 3, "total": 60}}"#,
+        )
+        .unwrap();
+        let paging = response.paging().unwrap();
+        assert_eq!(
+            (paging.page_no, paging.page_max, paging.count),
+            (Some(1), Some(3), Some(60))
+        );
+
+        let response: Response =
+            serde_json::from_str(r#"{"result": "success", "data": []}"#).unwrap();
+        assert_eq!(response.paging(), None);
+    }
+
+    #[test]
+    fn test_response_paging_next_params() {
+        let mut current = Param::new();
+        current.insert("page_no".to_string(), Value::from(1));
+
+        let response: Response = serde_json::from_str(
+            r#"{"result": "success", "data": [], "paging": {"page_no": 1, "page_max": 2}}"#,
+        )
+        .unwrap();
+        let next = response.paging_next_params(&current).unwrap();
+        assert_eq!(next["page_no"], 2);
+
+        let response: Response = serde_json::from_str(
+            r#"{"result": "success", "data": [], "paging": {"page_no": 2, "page_max": 2}}"#,
+        )
+        .unwrap();
+        assert_eq!(response.paging_next_params(&next), None);
+    }
+
+    #[test]
+    fn test_response_parse_body() {
+        let (response, raw) =
+            Response::parse_body(br#"{"result": "success", "data": {"id": 1}}"#).unwrap();
+        assert!(!raw);
+        assert_eq!(response.get_i64("id"), Some(1));
+
+        let (response, raw) =
+            Response::parse_body(br#"{"access_token": "abc", "expires_in": 3600}"#).unwrap();
+        assert!(raw);
+        assert_eq!(response.result, "success");
+        assert_eq!(response.get_string("access_token").as_deref(), Some("abc"));
+
+        let (response, raw) = Response::parse_body(b"[1, 2]").unwrap();
+        assert!(raw);
+        assert_eq!(response.apply::<Vec<i32>>().unwrap(), [1, 2]);
+
+        assert!(Response::parse_body(b"not json").is_err());
+    }
+
+    #[test]
+    fn test_response_into_data() {
+        let response: Response =
+            serde_json::from_str(r#"{"result": "success", "data": {"id": 1}}"#).unwrap();
+        assert_eq!(response.into_data().unwrap(), serde_json::json!({"id": 1}));
+
+        let response: Response = serde_json::from_str(r#"{"result": "success"}"#).unwrap();
+        assert!(matches!(
+            response.into_data(),
+            Err(crate::error::RestError::EmptyData)
+        ));
     }
 
     #[test]
@@ -169,6 +890,93 @@ mod tests {
         assert_eq!(name, Some("test".to_string()));
     }
 
+    #[test]
+    fn test_response_try_get() {
+        let json = r#"{
+            "result": "success",
+            "data": {"user": {"name": "test", "age": 3}, "items": [{"id": 1}], "list": []}
+        }"#;
+        let response: Response = serde_json::from_str(json).unwrap();
+        assert_eq!(response.try_get_string("user/name").unwrap(), "test");
+        assert_eq!(response.try_get("items/0/id").unwrap(), 1);
+
+        let message = |path: &str| response.try_get_string(path).unwrap_err().to_string();
+        assert!(message("user/email").contains("path 'user/email': no field 'email' in 'user'"));
+        assert!(message("nope").contains("no field 'nope' in 'data'"));
+        assert!(message("list/name").contains("expected object at 'list', found array"));
+        assert!(message("items/2").contains("index 2 out of range at 'items' (length 1)"));
+        assert!(message("user/age/x").contains("expected object at 'user/age', found number"));
+        assert!(message("user/age").contains("expected string, found number"));
+
+        let empty: Response = serde_json::from_str(r#"{"result":"success"}"#).unwrap();
+        assert!(empty
+            .try_get("a")
+            .unwrap_err()
+            .to_string()
+            .contains("no data"));
+    }
+
+    #[test]
+    fn test_response_get_bytes() {
+        let json = r#"{
+            "result": "success",
+            "data": {"std": "+/8=", "url": "-_8", "plain": "aGk=", "bad": "a$b", "n": 1}
+        }"#;
+        let response: Response = serde_json::from_str(json).unwrap();
+        assert_eq!(response.get_bytes("std").unwrap(), [0xfb, 0xff]);
+        assert_eq!(response.get_bytes("url").unwrap(), [0xfb, 0xff]);
+        assert_eq!(response.get_bytes("plain").unwrap(), b"hi");
+
+        let err = response.get_bytes("bad").unwrap_err().to_string();
+        assert!(err.contains("path 'bad': invalid base64"), "{}", err);
+        assert!(response.get_bytes("n").is_err());
+        assert!(response.get_bytes("missing").is_err());
+    }
+
+    #[test]
+    fn test_response_get_integers() {
+        // 2^53 + 1 is the first integer an f64 cannot represent.
+        let json = r#"{
+            "result": "success",
+            "data": {"id": 9007199254740993, "neg": -9007199254740993, "str": "9007199254740993", "f": 1.5}
+        }"#;
+
+        let response: Response = serde_json::from_str(json).unwrap();
+        assert_eq!(response.get_i64("id"), Some(9007199254740993));
+        assert_eq!(response.get_u64("id"), Some(9007199254740993));
+        assert_eq!(response.get_i64("neg"), Some(-9007199254740993));
+        assert_eq!(response.get_u64("neg"), None);
+        assert_eq!(response.get_i64("str"), Some(9007199254740993));
+        assert_eq!(response.get_i64("f"), None);
+
+        #[derive(Deserialize)]
+        struct Item {
+            id: u64,
+        }
+        let item: Item = response.apply().unwrap();
+        assert_eq!(item.id, 9007199254740993);
+    }
+
+    #[test]
+    fn test_response_get_f64_and_bool() {
+        let json = r#"{
+            "result": "success",
+            "data": {"f": 1.5, "i": 2, "s": "0.25", "t": true, "ts": "false", "n": null, "x": "yes"}
+        }"#;
+
+        let response: Response = serde_json::from_str(json).unwrap();
+        assert_eq!(response.get_f64("f"), Some(1.5));
+        assert_eq!(response.get_f64("i"), Some(2.0));
+        assert_eq!(response.get_f64("s"), Some(0.25));
+        assert_eq!(response.get_f64("t"), None);
+        assert_eq!(response.get_bool("t"), Some(true));
+        assert_eq!(response.get_bool("ts"), Some(false));
+        assert_eq!(response.get_bool("n"), None);
+        assert_eq!(response.get_bool("x"), None);
+        assert_eq!(response.get_bool("i"), None);
+        assert_eq!(response.get_bool("missing"), None);
+    }
+
     #[test]
     fn test_response_apply() {
         #[derive(Deserialize)]
@@ -184,5 +992,63 @@ mod tests {
         let response: Response = serde_json::from_str(json).unwrap();
         let user: User = response.apply().unwrap();
         assert_eq!(user.name, "test");
+
+        let user: User = response.take_apply().unwrap();
+        assert_eq!(user.name, "test");
+    }
+
+    #[cfg(feature = "json-path")]
+    #[test]
+    fn test_apply_error_path() {
+        #[derive(Debug, Deserialize)]
+        struct Item {
+            #[allow(dead_code)]
+            name: String,
+        }
+        #[derive(Debug, Deserialize)]
+        struct List {
+            #[allow(dead_code)]
+            items: Vec<Item>,
+        }
+
+        let response: Response = serde_json::from_str(
+            r#"{"result": "success", "data": {"items": [{"name": "a"}, {"name": 2}]}}"#,
+        )
+        .unwrap();
+        let err = response.apply::<List>().unwrap_err().to_string();
+        assert!(
+            err.starts_with("JSON error: data.items[1].name: invalid type"),
+            "{}",
+            err
+        );
+
+        // Root-level failures have no path to report.
+        let err = response.apply::<Vec<Item>>().unwrap_err().to_string();
+        assert!(err.starts_with("JSON error: invalid type"), "{}", err);
+    }
+
+    #[test]
+    fn test_expect_success() {
+        let response = Response::from_raw(serde_json::json!(1)).expect_success("raw");
+        assert_eq!(response.data, Some(serde_json::json!(1)));
+        let value = Ok::<_, crate::error::RestError>(2).expect_ok("value");
+        assert_eq!(value, 2);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "user lookup:\nerror: REST API error: no such user\nkind: Api\ncode: 404"
+    )]
+    fn test_expect_success_panics() {
+        let response: Response =
+            serde_json::from_str(r#"{"result": "error", "error": "no such user", "code": 404}"#)
+                .unwrap();
+        response.expect_success("user lookup");
+    }
+
+    #[test]
+    #[should_panic(expected = "listing:\nerror: response has no data\nkind: EmptyData")]
+    fn test_expect_ok_panics() {
+        Err::<(), _>(crate::error::RestError::EmptyData).expect_ok("listing");
     }
 }
@@ -1,22 +1,148 @@
-use crate::apikey::ApiKey;
+use crate::apikey::{pairs_input, ApiKey, ParamNames, SigningInput};
+use crate::cache::{cache_key, Cache};
 use crate::client::Config;
+use crate::download::DownloadProgressFn;
+use crate::endpoint::EndpointInfo;
 use crate::error::{RestError, Result};
+use crate::health::HealthReport;
+use crate::job::{Job, JobWaitOptions};
+use crate::ndjson::NdjsonStream;
+use crate::paginate::PageIterator;
 use crate::response::Response;
+use crate::retry::{RetryEvent, RetryHookFn, RetryOutcome, RetryPolicy};
 use crate::token::Token;
+use crate::trace::{RequestTrace, TracingTransport};
+use crate::transport::{HttpRequest, RsurlTransport, Transport};
 use serde::Serialize;
 use std::collections::HashMap;
+use std::io::Read;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-/// Overall request timeout for REST calls.
-const REST_TIMEOUT: Duration = Duration::from_secs(300);
-/// Connection establishment timeout.
-const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Client identifier sent to the server with every REST request, in the
+/// `Sec-Rest-Client` header.
+pub const PROTOCOL_VERSION: &str = concat!("klbfw-rs/", env!("CARGO_PKG_VERSION"));
+
+/// Version of this crate.
+pub fn version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+
+/// Authentication method configured on a [`Client`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AuthMethod {
+    /// OAuth2 bearer token
+    Token,
+    /// Ed25519-signed API key
+    ApiKey,
+    /// API key signature identifying the application, plus a bearer token
+    /// acting as the user
+    ApiKeyAndToken,
+}
+
+/// How a [`Client`] puts the parameters of `GET`, `HEAD` and `OPTIONS`
+/// requests in the query string.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum QueryEncoding {
+    /// All parameters as one JSON document in the `_` query parameter, as the
+    /// API expects
+    #[default]
+    Json,
+    /// One query parameter per top-level parameter, for gateways expecting
+    /// plain query strings
+    ///
+    /// Arrays become a repeated name (`?ids=1&ids=2`, nothing for an empty
+    /// array), strings are sent as is, `null` as an empty value, and numbers,
+    /// booleans and objects as JSON text. Parameters that are not an object
+    /// are sent as [`Json`](Self::Json).
+    Repeated,
+}
+
+/// Header carrying the idempotency key of a request
+const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
+
+/// Settings for a single request, see [`Client::do_request_with_options`]
+///
+/// ```no_run
+/// use klbfw::{Client, RequestOptions};
+///
+/// let ctx = Client::new();
+/// let options = RequestOptions::new().with_new_idempotency_key();
+/// ctx.do_request_with_options("Order", "POST", serde_json::json!({"item": 1}), &options)?;
+/// # Ok::<(), klbfw::RestError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    idempotency_key: Option<String>,
+    timeout: Option<Duration>,
+    headers: Vec<(String, String)>,
+}
+
+impl RequestOptions {
+    /// Options changing nothing from the client's settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send `key` in the `Idempotency-Key` header
+    ///
+    /// Lets the server recognize a request sent again, e.g. after a lost
+    /// response, and not repeat its effect, such as creating a resource
+    /// twice. The same key is sent with every retry, so with a
+    /// [`RetryPolicy`] even `POST` and `PATCH` requests are retried.
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+
+    /// Send a new random idempotency key (a UUID), see
+    /// [`with_idempotency_key`](Self::with_idempotency_key)
+    ///
+    /// To resend the same request later, keep these options, or read the
+    /// key back with [`idempotency_key`](Self::idempotency_key).
+    pub fn with_new_idempotency_key(self) -> Self {
+        self.with_idempotency_key(uuid::Uuid::new_v4().to_string())
+    }
+
+    /// Use `timeout` instead of [`Config::timeout`] for each HTTP exchange
+    /// of the request, like [`Client::do_request_with_timeout`]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Add a header to the request, after the client's custom headers
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// The idempotency key sent, if any
+    pub fn idempotency_key(&self) -> Option<&str> {
+        self.idempotency_key.as_deref()
+    }
+
+    /// The timeout of the request, if set
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// The headers added to the request
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+}
 
 /// Client for REST API requests.
 ///
 /// Holds the configuration, optional authentication (token or API key), and any
 /// custom headers, and exposes methods to make requests.
+///
+/// Cloning is cheap: shared state (the transport and the token, so renewals
+/// are seen by every clone) is held behind `Arc`s, and hooks added to the
+/// client must be stored the same way (`Arc<dyn Fn ...>`) to keep it so.
 #[derive(Clone)]
 pub struct Client {
     /// Configuration
@@ -27,6 +153,25 @@ pub struct Client {
     api_key: Option<ApiKey>,
     /// Extra headers applied to every request (in insertion order)
     headers: Vec<(String, String)>,
+    /// Caller-provided id sent with every request and attached to its errors
+    correlation_id: Option<String>,
+    /// Sends the built HTTP requests (shared between clones)
+    transport: Arc<dyn Transport>,
+    /// Ask raw endpoints to wrap their output in the standard envelope
+    noraw: bool,
+    /// Send the token along with the API key signature
+    combined_auth: bool,
+    /// Query string form of `GET`-like request parameters
+    query_encoding: QueryEncoding,
+    /// Where `GET` responses are looked up and stored (shared between clones)
+    cache: Option<Arc<dyn Cache>>,
+    /// Receives an event for every retried attempt
+    retry_hook: Option<RetryHookFn>,
+    /// When failed requests are sent again
+    retry_policy: Option<RetryPolicy>,
+    /// JSON Schema the data of successful responses must match
+    #[cfg(feature = "schema-validation")]
+    validator: Option<Arc<jsonschema::Validator>>,
 }
 
 impl Client {
@@ -37,6 +182,16 @@ impl Client {
             token: Arc::new(Mutex::new(None)),
             api_key: None,
             headers: Vec::new(),
+            correlation_id: None,
+            transport: Arc::new(RsurlTransport),
+            noraw: false,
+            combined_auth: false,
+            query_encoding: QueryEncoding::default(),
+            cache: None,
+            retry_hook: None,
+            retry_policy: None,
+            #[cfg(feature = "schema-validation")]
+            validator: None,
         }
     }
 
@@ -47,6 +202,16 @@ impl Client {
             token: Arc::new(Mutex::new(None)),
             api_key: None,
             headers: Vec::new(),
+            correlation_id: None,
+            transport: Arc::new(RsurlTransport),
+            noraw: false,
+            combined_auth: false,
+            query_encoding: QueryEncoding::default(),
+            cache: None,
+            retry_hook: None,
+            retry_policy: None,
+            #[cfg(feature = "schema-validation")]
+            validator: None,
         }
     }
 
@@ -58,15 +223,30 @@ impl Client {
 
     /// Set the API key
     pub fn with_api_key(mut self, api_key: ApiKey) -> Self {
+        crate::error::mask_in_reports(&api_key.param_names().sign);
         self.api_key = Some(api_key);
         self
     }
 
+    /// Authenticate with both an API key and a user token
+    ///
+    /// For endpoints that need the application identified by its API key
+    /// while acting as a user: requests are signed with `api_key` and also
+    /// carry `token` in the `Authorization` header. (With
+    /// [`with_api_key`](Self::with_api_key) and [`with_token`](Self::with_token)
+    /// alone, the API key wins and the token is not sent.)
+    pub fn with_api_key_and_token(mut self, api_key: ApiKey, token: Token) -> Self {
+        self.combined_auth = true;
+        self.with_api_key(api_key).with_token(token)
+    }
+
     /// Add a custom header applied to every request (builder style).
     ///
-    /// Custom headers are sent in addition to the headers the client sets
-    /// automatically (`Authorization`, `Content-Type`, ...); they do not
-    /// replace them. Call multiple times to add several headers, including
+    /// Custom headers are sent along with the headers the client sets
+    /// itself, which take precedence: a custom `Sec-Rest-Http` or
+    /// `Sec-Rest-Client` is never sent, nor a custom `Authorization`,
+    /// `Content-Type` or `X-Correlation-Id` when the client sets one for the
+    /// request. Call multiple times to add several headers, including
     /// repeated header names.
     pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
         self.headers.push((name.into(), value.into()));
@@ -97,6 +277,193 @@ impl Client {
         &self.headers
     }
 
+    /// Tag every request with a correlation id (builder style).
+    ///
+    /// The id is sent in the `X-Correlation-Id` header, and every error
+    /// returned by this client is wrapped in [`RestError::Correlated`]
+    /// carrying it, so client and server logs can be stitched together even
+    /// when the server never assigned an `X-Request-Id`.
+    pub fn with_correlation_id(mut self, id: impl Into<String>) -> Self {
+        self.correlation_id = Some(id.into());
+        self
+    }
+
+    /// The correlation id sent with every request, if set
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.correlation_id.as_deref()
+    }
+
+    /// Attach this client's correlation id, if any, to `err`.
+    pub(crate) fn correlate(&self, err: RestError) -> RestError {
+        match &self.correlation_id {
+            Some(id) if err.correlation_id().is_none() => RestError::Correlated {
+                correlation_id: id.clone(),
+                source: Box::new(err),
+            },
+            _ => err,
+        }
+    }
+
+    /// Send requests through `transport` instead of the network (builder
+    /// style).
+    ///
+    /// Meant for tests and tooling such as recording and replaying
+    /// interactions; see [`transport`](crate::transport). Clones of this
+    /// client share the transport.
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Serve `GET` requests from `cache` when possible (builder style).
+    ///
+    /// Successful `GET` responses are stored in the cache, and later `GET`
+    /// requests with the same path and parameters get the stored response,
+    /// with [`Response::from_cache`] set, without a network call. See
+    /// [`cache`](crate::cache). Clones of this client share the cache.
+    ///
+    /// Entries are keyed by the client's credentials too (the API key id and
+    /// the access token), so clients of different accounts can share a
+    /// cache. A renewed token starts with an empty cache.
+    pub fn with_cache(mut self, cache: Arc<dyn Cache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Who the requests are sent as, for cache keys: the API key id and the
+    /// access token, whichever are set.
+    fn auth_identity(&self) -> String {
+        let key = self.api_key.as_ref().map(|key| key.key_id.as_str());
+        let token = self.token.lock().unwrap();
+        let access = token.as_ref().map(|t| t.access_token.as_str());
+        format!("key:{}\ntoken:{}", key.unwrap_or(""), access.unwrap_or(""))
+    }
+
+    /// A clone of this client that never uses the cache, for requests whose
+    /// answer is expected to change.
+    pub(crate) fn without_cache(&self) -> Client {
+        let mut client = self.clone();
+        client.cache = None;
+        client
+    }
+
+    /// Call `hook` for every retried attempt and when retries run out
+    /// (builder style).
+    ///
+    /// Each [`RetryEvent`] has the path, attempt number, error, request id
+    /// and backoff delay. See [`retry`](crate::retry).
+    pub fn with_retry_hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&RetryEvent<'_>) + Send + Sync + 'static,
+    {
+        self.retry_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Retry requests failing transiently as set by `policy` (builder
+    /// style).
+    ///
+    /// Without a policy, requests are sent once (besides the retry after a
+    /// token renewal). See [`RetryPolicy`] for what is retried and when.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// The retry policy of this client, if any
+    pub fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.retry_policy.as_ref()
+    }
+
+    /// Pass a retry event to the hook, and print it in debug mode.
+    pub(crate) fn report_retry(&self, event: &RetryEvent<'_>) {
+        if self.config.debug() {
+            eprintln!("{}", event);
+        }
+        if let Some(hook) = &self.retry_hook {
+            hook(event);
+        }
+    }
+
+    /// Check the data of every successful response against a JSON Schema
+    /// (builder style).
+    ///
+    /// A response whose `data` does not match `schema` fails with
+    /// [`RestError::SchemaViolation`] listing every mismatch. Meant for
+    /// integration tests and staging, to catch changes in the server's
+    /// response shapes early; responses served from a
+    /// [cache](Self::with_cache) are not checked again. Fails if `schema`
+    /// itself is invalid.
+    ///
+    /// Available with the `schema-validation` feature.
+    #[cfg(feature = "schema-validation")]
+    pub fn with_response_validator(mut self, schema: &serde_json::Value) -> Result<Self> {
+        let validator = jsonschema::validator_for(schema)
+            .map_err(|e| RestError::Other(format!("invalid JSON schema: {}", e)))?;
+        self.validator = Some(Arc::new(validator));
+        Ok(self)
+    }
+
+    /// Check the data of a successful response against the configured
+    /// schema, if any.
+    #[cfg(feature = "schema-validation")]
+    fn validate_response(&self, response: &Response) -> Result<()> {
+        let Some(validator) = &self.validator else {
+            return Ok(());
+        };
+        let data = response.data.as_ref().unwrap_or(&serde_json::Value::Null);
+        let errors: Vec<String> = validator
+            .iter_errors(data)
+            .map(|e| format!("data{}: {}", e.instance_path, e))
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(RestError::SchemaViolation { errors })
+        }
+    }
+
+    /// Add `noraw` to the parameters of every request (builder style).
+    ///
+    /// Raw endpoints (such as `OAuth2:token`) return their data without the
+    /// standard response envelope unless `noraw` is set. Either way is
+    /// handled: a raw body is wrapped with [`Response::from_raw`]. Setting
+    /// `noraw` keeps the envelope's error details for such endpoints.
+    /// Only applies to requests whose parameters are an object.
+    pub fn with_noraw(mut self, noraw: bool) -> Self {
+        self.noraw = noraw;
+        self
+    }
+
+    /// Set how the parameters of `GET`, `HEAD` and `OPTIONS` requests are
+    /// encoded in the query string (builder style)
+    ///
+    /// Defaults to [`QueryEncoding::Json`]. With an API key, the signature
+    /// covers repeated names in the order they are sent.
+    pub fn with_query_encoding(mut self, encoding: QueryEncoding) -> Self {
+        self.query_encoding = encoding;
+        self
+    }
+
+    /// Apply a bundle of timeout and retry settings (builder style)
+    ///
+    /// Replaces any [retry policy](Self::with_retry) with the profile's;
+    /// see [`TimeoutProfile`](crate::client::TimeoutProfile) for the values.
+    pub fn with_profile(mut self, profile: crate::client::TimeoutProfile) -> Self {
+        self.config = self.config.with_profile(profile);
+        self.retry_policy = Some(profile.retry_policy());
+        self
+    }
+
+    /// Identify the application with a `User-Agent` header (builder style)
+    ///
+    /// See [`Config::with_user_agent`]. A `User-Agent` set with
+    /// [`with_header`](Self::with_header) takes precedence.
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.config = self.config.with_user_agent(user_agent);
+        self
+    }
+
     /// Enable debug mode
     pub fn with_debug(mut self, debug: bool) -> Self {
         self.config.set_debug(debug);
@@ -108,6 +475,86 @@ impl Client {
         &self.config
     }
 
+    /// The authentication method requests will use, if any.
+    ///
+    /// When both an API key and a token are set, the API key wins (the token is
+    /// not sent), unless they were set together with
+    /// [`with_api_key_and_token`](Self::with_api_key_and_token). This is a
+    /// local check; no request is made.
+    pub fn auth_method(&self) -> Option<AuthMethod> {
+        let has_token = self.token.lock().unwrap().is_some();
+        if self.api_key.is_some() && self.combined_auth && has_token {
+            Some(AuthMethod::ApiKeyAndToken)
+        } else if self.api_key.is_some() {
+            Some(AuthMethod::ApiKey)
+        } else if has_token {
+            Some(AuthMethod::Token)
+        } else {
+            None
+        }
+    }
+
+    /// Whether this context has usable credentials: an API key, or a token
+    /// that has not expired or can be renewed (it has a refresh token and a
+    /// client ID).
+    ///
+    /// This is a local check; the credentials are not validated against the
+    /// server.
+    pub fn is_authenticated(&self) -> bool {
+        if self.api_key.is_some() {
+            return true;
+        }
+        match &*self.token.lock().unwrap() {
+            Some(token) => {
+                !token.is_expired() || (token.has_refresh_token() && token.has_client_id())
+            }
+            None => false,
+        }
+    }
+
+    /// A multi-line description of this context that is safe to share.
+    ///
+    /// Shows the protocol version, target URL, debug flag, request deadline,
+    /// which credentials are set and the custom header names. Secrets are
+    /// never included: the API key shows only its `key_id`, the token only
+    /// whether it is set, and header values are redacted. Meant for bug
+    /// reports and logs.
+    pub fn debug_summary(&self) -> String {
+        let auth = match self.auth_method() {
+            Some(AuthMethod::ApiKey) => "api_key",
+            Some(AuthMethod::ApiKeyAndToken) => "api_key+token",
+            Some(AuthMethod::Token) => "token",
+            None => "none",
+        };
+        let api_key = match &self.api_key {
+            Some(key) => format!("set (key_id: {})", key.key_id),
+            None => "unset".to_string(),
+        };
+        let token = if self.token.lock().unwrap().is_some() {
+            "set <redacted>"
+        } else {
+            "unset"
+        };
+        let deadline = match self.config.request_deadline() {
+            Some(budget) => format!("{:?}", budget),
+            None => "none".to_string(),
+        };
+        let headers: Vec<&str> = self.headers.iter().map(|(k, _)| k.as_str()).collect();
+
+        format!(
+            "client: {}\nbase_url: {}\ndebug: {}\nrequest_deadline: {}\ncorrelation_id: {}\nauth: {}\napi_key: {}\ntoken: {}\nheaders: [{}] (values redacted)",
+            PROTOCOL_VERSION,
+            self.config.base_url(),
+            self.config.debug(),
+            deadline,
+            self.correlation_id.as_deref().unwrap_or("none"),
+            auth,
+            api_key,
+            token,
+            headers.join(", "),
+        )
+    }
+
     /// Make a REST API request and unmarshal the response data into the target type
     ///
     /// # Arguments
@@ -123,7 +570,42 @@ impl Client {
         P: Serialize,
     {
         let response = self.do_request(path, method, param)?;
-        response.apply()
+        response.take_apply().map_err(|e| self.correlate(e))
+    }
+
+    /// Like [`apply`](Self::apply), but tolerates `[]` where an empty object
+    /// is expected
+    ///
+    /// See [`Response::apply_lenient`].
+    pub fn apply_lenient<T, P>(&self, path: &str, method: &str, param: P) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+        P: Serialize,
+    {
+        self.do_request(path, method, param)?
+            .apply_lenient()
+            .map_err(|e| self.correlate(e))
+    }
+
+    /// `GET` an endpoint without parameters and unmarshal the response data
+    ///
+    /// Shorthand for `apply(path, "GET", json!({}))`.
+    pub fn get<T>(&self, path: &str) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.apply(path, "GET", serde_json::Map::new())
+    }
+
+    /// `POST` parameters to an endpoint and unmarshal the response data
+    ///
+    /// Shorthand for `apply(path, "POST", param)`.
+    pub fn post<T, P>(&self, path: &str, param: P) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+        P: Serialize,
+    {
+        self.apply(path, "POST", param)
     }
 
     /// Execute a REST API request and return the raw Response object
@@ -139,129 +621,614 @@ impl Client {
     where
         P: Serialize,
     {
-        let param_json = serde_json::to_value(param)?;
-        self.request_inner(path, method, &param_json, true)
+        let deadline = self
+            .config
+            .request_deadline()
+            .and_then(|budget| Instant::now().checked_add(budget));
+        let param_json = serde_json::to_value(param).map_err(|e| self.correlate(e.into()))?;
+        self.request_retrying(path, method, Payload::Params(&param_json), deadline)
+            .map_err(|e| self.correlate(e))
     }
 
-    /// Inner request implementation.
+    /// Execute a REST API request and capture its HTTP exchange
     ///
-    /// `allow_renew` guards token renewal so an expired token triggers exactly
-    /// one retry.
-    fn request_inner(
+    /// Behaves like [`do_request`](Self::do_request), and also returns the
+    /// exact URL, headers and bodies sent and received, for attaching to a
+    /// bug report. The trace holds credentials; share its
+    /// [`redacted`](RequestTrace::redacted) copy.
+    pub fn do_request_traced<P>(
         &self,
         path: &str,
         method: &str,
-        param_json: &serde_json::Value,
-        allow_renew: bool,
-    ) -> Result<Response> {
-        // Build base URL
-        let base_url = self.config.base_url();
-        let url = format!("{}/_special/rest/{}", base_url, path);
+        param: P,
+    ) -> (Result<Response>, RequestTrace)
+    where
+        P: Serialize,
+    {
+        let tracer = Arc::new(TracingTransport::new(self.transport.clone()));
+        let client = self.clone().with_transport(tracer.clone());
+        let result = client.do_request(path, method, param);
+        let trace = tracer.take().with_sign_param(self.sign_param());
+        (result, trace)
+    }
 
-        let mut query_params: HashMap<String, String> = HashMap::new();
-        let mut body_bytes: Vec<u8> = Vec::new();
+    /// Names of the signing parameters of this client's requests: those of
+    /// its API key (see [`ApiKey::with_param_names`]), or the defaults
+    ///
+    /// Requests carry them in [`HttpRequest::param_names`] for transports
+    /// such as the record/replay ones, which leave them out of the match.
+    pub fn param_names(&self) -> ParamNames {
+        self.api_key
+            .as_ref()
+            .map(|key| key.param_names().clone())
+            .unwrap_or_default()
+    }
 
-        match method {
-            "GET" | "HEAD" | "OPTIONS" => {
-                // Parameters go in query string
-                let param_str = serde_json::to_string(param_json)?;
-                query_params.insert("_".to_string(), param_str);
-            }
-            "PUT" | "POST" | "PATCH" => {
-                // Parameters go in request body
-                body_bytes = serde_json::to_vec(param_json)?;
-            }
-            "DELETE" => {
-                // No parameters
-            }
-            _ => {
-                return Err(RestError::RequestBuild(format!(
-                    "Unsupported HTTP method: {}",
-                    method
-                )))
-            }
-        }
+    /// Name of the API key's signature parameter, if any.
+    fn sign_param(&self) -> Option<&str> {
+        self.api_key
+            .as_ref()
+            .map(|key| key.param_names().sign.as_str())
+    }
 
-        // Apply API key authentication if present
-        if let Some(ref api_key) = self.api_key {
-            api_key.apply_params(method, path, &mut query_params, &body_bytes)?;
-        }
+    /// Render a REST API request as a runnable curl command, without sending
+    /// it
+    ///
+    /// The command carries exactly what [`do_request`](Self::do_request)
+    /// would send: URL (with API key signature), headers and body. A signed
+    /// request is only valid for a short time after it is built. Set `redact`
+    /// to mask credentials (the `Authorization` header and the `_sign`
+    /// signature, whatever its name), e.g. for a bug report; the command then
+    /// no longer authenticates.
+    pub fn curl_command<P>(
+        &self,
+        path: &str,
+        method: &str,
+        param: P,
+        redact: bool,
+    ) -> Result<String>
+    where
+        P: Serialize,
+    {
+        validate_path(path)?;
+        let param_json = serde_json::to_value(param)?;
+        let (request, _) = self.build_request(
+            path,
+            method,
+            Payload::Params(&param_json),
+            self.config.timeout(),
+        )?;
+        Ok(crate::trace::curl_command(
+            &request,
+            redact,
+            self.sign_param(),
+        ))
+    }
 
-        // Build the full URL with an (optional) query string.
-        let full_url = if query_params.is_empty() {
-            url
-        } else {
-            let query = form_urlencoded::Serializer::new(String::new())
-                .extend_pairs(query_params.iter())
-                .finish();
-            format!("{}?{}", url, query)
+    /// What the API key signature of a request covers, without sending it
+    ///
+    /// Builds the request like [`do_request`](Self::do_request) and returns
+    /// the [`SigningInput`] its signature was computed over, to debug
+    /// signature mismatches. `GET` and `POST` requests with the same
+    /// parameters sign different inputs; see [`SigningInput`]. `None` when no
+    /// API key is configured.
+    pub fn signing_input<P>(
+        &self,
+        path: &str,
+        method: &str,
+        param: P,
+    ) -> Result<Option<SigningInput>>
+    where
+        P: Serialize,
+    {
+        let Some(api_key) = &self.api_key else {
+            return Ok(None);
         };
+        validate_path(path)?;
+        let param_json = serde_json::to_value(param)?;
+        let (request, _) = self.build_request(
+            path,
+            method,
+            Payload::Params(&param_json),
+            self.config.timeout(),
+        )?;
 
-        // Snapshot the current token (used only when not authenticating by key).
-        let current_token = if self.api_key.is_none() {
-            self.token.lock().unwrap().clone()
-        } else {
-            None
-        };
+        let query = request.url.split_once('?').map_or("", |(_, q)| q);
+        let pairs: Vec<(String, String)> = form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+        let body_hash = purecrypto::hash::sha256(&request.body);
+        Ok(Some(pairs_input(
+            method,
+            path,
+            pairs.iter().map(|(k, v)| (k, v)),
+            &body_hash,
+            &api_key.param_names().sign,
+        )))
+    }
 
-        // Build the request.
-        let mut request = rsurl::Request::new(method, &full_url)?
-            .header("Sec-Rest-Http", "false")
-            .max_time(REST_TIMEOUT)
-            .connect_timeout(CONNECT_TIMEOUT);
+    /// Execute a REST API request that must complete before `deadline`
+    ///
+    /// Like [`do_request`](Self::do_request), but the whole call, including
+    /// token renewal, fails with [`RestError::DeadlineExceeded`] once
+    /// `deadline` passes. Overrides [`Config::request_deadline`].
+    pub fn do_request_deadline<P>(
+        &self,
+        path: &str,
+        method: &str,
+        param: P,
+        deadline: Instant,
+    ) -> Result<Response>
+    where
+        P: Serialize,
+    {
+        let param_json = serde_json::to_value(param).map_err(|e| self.correlate(e.into()))?;
+        let payload = Payload::Params(&param_json);
+        self.request_retrying(path, method, payload, Some(deadline))
+            .map_err(|e| self.correlate(e))
+    }
 
-        // Apply user-supplied custom headers before the client-managed ones so
-        // that Authorization/Content-Type set below take precedence.
-        for (name, value) in &self.headers {
-            request = request.header(name, value);
-        }
+    /// Execute a REST API request with its own timeout
+    ///
+    /// Like [`do_request`](Self::do_request), with `timeout` instead of
+    /// [`Config::timeout`] for each HTTP exchange of this call (the request,
+    /// and the token renewal and retry if the token expired). Other calls
+    /// keep the client's timeout; [`Config::request_deadline`] still applies.
+    pub fn do_request_with_timeout<P>(
+        &self,
+        path: &str,
+        method: &str,
+        param: P,
+        timeout: Duration,
+    ) -> Result<Response>
+    where
+        P: Serialize,
+    {
+        // Clones share the token, so a renewal here is seen by `self`.
+        let mut client = self.clone();
+        client.config = client.config.with_timeout(timeout);
+        client.do_request(path, method, param)
+    }
 
-        if let Some(ref token) = current_token {
-            request = request.header("Authorization", &format!("Bearer {}", token.access_token));
+    /// Execute a REST API request with per-call settings
+    ///
+    /// Like [`do_request`](Self::do_request), with the idempotency key,
+    /// timeout and headers of `options` for this call only.
+    pub fn do_request_with_options<P>(
+        &self,
+        path: &str,
+        method: &str,
+        param: P,
+        options: &RequestOptions,
+    ) -> Result<Response>
+    where
+        P: Serialize,
+    {
+        // Clones share the token, so a renewal here is seen by `self`.
+        let mut client = self.clone();
+        if let Some(timeout) = options.timeout {
+            client.config = client.config.with_timeout(timeout);
         }
-
-        if !body_bytes.is_empty() {
-            request = request
-                .header("Content-Type", "application/json")
-                .body(body_bytes);
+        client.headers.extend(options.headers.iter().cloned());
+        if let Some(ref key) = options.idempotency_key {
+            client
+                .headers
+                .retain(|(name, _)| !name.eq_ignore_ascii_case(IDEMPOTENCY_KEY));
+            client
+                .headers
+                .push((IDEMPOTENCY_KEY.to_string(), key.clone()));
         }
+        client.do_request(path, method, param)
+    }
 
-        // Execute request
-        let start = std::time::Instant::now();
-        let http_response = request.send()?;
-        let status = http_response.status;
+    /// Execute a REST API request with a raw, non-JSON body
+    ///
+    /// Sends the content of `reader` as the body of a `PUT`, `POST` or
+    /// `PATCH` request with the given `content_type`, for endpoints taking a
+    /// document rather than JSON parameters.
+    ///
+    /// The body is read into memory before it is sent: the underlying HTTP
+    /// client only sends bodies from memory, and API key signing needs the
+    /// hash of the whole body up front anyway. Buffering also lets the body be
+    /// resent after a token renewal. When `content_length` is given it sizes
+    /// the buffer, and a reader yielding a different number of bytes is an
+    /// error. For large files, use [`upload`](crate::upload()) instead.
+    pub fn do_request_body<R: Read>(
+        &self,
+        path: &str,
+        method: &str,
+        mut reader: R,
+        content_type: &str,
+        content_length: Option<u64>,
+    ) -> Result<Response> {
+        let deadline = self
+            .config
+            .request_deadline()
+            .and_then(|budget| Instant::now().checked_add(budget));
 
-        // Get X-Request-Id header
-        let request_id = http_response.header("X-Request-Id").map(|s| s.to_string());
+        let mut body = Vec::with_capacity(content_length.unwrap_or(0) as usize);
+        reader
+            .read_to_end(&mut body)
+            .map_err(|e| self.correlate(e.into()))?;
+        if let Some(expected) = content_length {
+            if body.len() as u64 != expected {
+                return Err(self.correlate(RestError::RequestBuild(format!(
+                    "body is {} bytes, expected {}",
+                    body.len(),
+                    expected
+                ))));
+            }
+        }
 
-        let body = http_response.body;
+        let payload = Payload::Raw {
+            body: &body,
+            content_type,
+        };
+        self.request_retrying(path, method, payload, deadline)
+            .map_err(|e| self.correlate(e))
+    }
 
-        if self.config.debug() {
-            let duration = start.elapsed();
-            eprintln!(
-                "[rest] {} {} => {:?} (status: {})",
+    /// Iterate over every item of a paginated list endpoint
+    ///
+    /// Pages are requested with `GET` as the iterator advances, following
+    /// either page numbers or cursor tokens, whichever the endpoint's `paging`
+    /// uses (see [`paginate`](crate::paginate)). `param` must serialize to an
+    /// object or null.
+    ///
+    /// ```no_run
+    /// # fn main() -> klbfw::Result<()> {
+    /// let ctx = klbfw::Client::new();
+    /// for user in ctx.paginate::<serde_json::Value, _>("User", serde_json::json!({}))? {
+    ///     println!("{}", user?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn paginate<T, P>(&self, path: &str, param: P) -> Result<PageIterator<'_, T>>
+    where
+        T: serde::de::DeserializeOwned,
+        P: Serialize,
+    {
+        self.paginate_with_method(path, "GET", param)
+    }
+
+    /// Iterate over every item of a paginated list endpoint requested with
+    /// `method`
+    ///
+    /// Like [`paginate`](Self::paginate), for list endpoints taking their
+    /// parameters with another method than `GET`, such as a `POST` search.
+    /// The paging parameters are added to `param` the same way.
+    pub fn paginate_with_method<T, P>(
+        &self,
+        path: &str,
+        method: &str,
+        param: P,
+    ) -> Result<PageIterator<'_, T>>
+    where
+        T: serde::de::DeserializeOwned,
+        P: Serialize,
+    {
+        PageIterator::new(self, path, method, serde_json::to_value(param)?)
+    }
+
+    /// Wait for a long-running server task to finish
+    ///
+    /// Polls the status of `job` (see [`Response::job_info`]) until it
+    /// completes and returns the last status response. A job that stops
+    /// without completing is a [`RestError::JobFailed`]. Uses the defaults
+    /// of [`JobWaitOptions`]: `Job/{id}` is polled every 2 seconds, for up
+    /// to 10 minutes.
+    pub fn wait_for_job(&self, job: &Job) -> Result<Response> {
+        self.wait_for_job_with_options(job, &JobWaitOptions::default())
+    }
+
+    /// Wait for a long-running server task to finish, polling as set in
+    /// `options`
+    ///
+    /// Like [`wait_for_job`](Self::wait_for_job); gives up with
+    /// [`RestError::JobTimeout`] after the options' timeout.
+    pub fn wait_for_job_with_options(
+        &self,
+        job: &Job,
+        options: &JobWaitOptions,
+    ) -> Result<Response> {
+        crate::job::wait(self, job, options)
+    }
+
+    /// Stream a response of newline-delimited JSON values
+    ///
+    /// For endpoints that answer with one JSON value per line (NDJSON) rather
+    /// than the usual envelope. The body is read and parsed line by line as
+    /// the returned iterator advances; see [`NdjsonStream`].
+    ///
+    /// Error responses (non-2xx status) are returned here, before streaming
+    /// starts. Expired tokens are not renewed.
+    ///
+    /// ```no_run
+    /// # fn main() -> klbfw::Result<()> {
+    /// let ctx = klbfw::Client::new();
+    /// for row in ctx.stream_ndjson("Data/Export", "GET", serde_json::json!({}))? {
+    ///     println!("{}", row?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stream_ndjson<P>(&self, path: &str, method: &str, param: P) -> Result<NdjsonStream>
+    where
+        P: Serialize,
+    {
+        self.stream_inner(path, method, param)
+            .map_err(|e| self.correlate(e))
+    }
+
+    fn stream_inner<P: Serialize>(
+        &self,
+        path: &str,
+        method: &str,
+        param: P,
+    ) -> Result<NdjsonStream> {
+        validate_path(path)?;
+        let deadline = self
+            .config
+            .request_deadline()
+            .and_then(|budget| Instant::now().checked_add(budget));
+        let timeout = time_left(deadline, self.config.timeout())?;
+        let param_json = serde_json::to_value(param)?;
+        let (request, _) =
+            self.build_request(path, method, Payload::Params(&param_json), timeout)?;
+
+        let mut response = self.transport.send_streaming(request)?;
+        let status = response.status;
+        if self.config.debug() {
+            eprintln!(
+                "[rest] {} {} => streaming (status: {})",
+                method, path, status
+            );
+        }
+        if (200..300).contains(&status) {
+            return Ok(NdjsonStream::new(response.body));
+        }
+
+        // Report enveloped errors like other requests do.
+        let mut body = Vec::new();
+        response.body.read_to_end(&mut body)?;
+        match Response::parse_body(&body) {
+            Ok((response, false))
+                if response.result == "redirect"
+                    && response.exception.as_deref() == Some("Exception\\Login") =>
+            {
+                Err(RestError::LoginRequired)
+            }
+            Ok((response, false)) if response.result != "success" => {
+                Err(RestError::from_response(response))
+            }
+            _ => Err(RestError::http(
+                status,
+                String::from_utf8_lossy(&body).to_string(),
+                None,
+            )),
+        }
+    }
+
+    /// Describe an endpoint: the methods it accepts and its parameters
+    ///
+    /// Sends an `OPTIONS` request to `path` and parses the returned
+    /// description. See [`EndpointInfo`].
+    pub fn describe(&self, path: &str) -> Result<EndpointInfo> {
+        self.apply_lenient(path, "OPTIONS", serde_json::json!({}))
+    }
+
+    /// Download a blob's content to `writer`, optionally resuming
+    ///
+    /// See [`download_blob`](crate::download_blob).
+    pub fn download_blob<W: std::io::Write + std::io::Seek>(
+        &self,
+        blob_id: &str,
+        writer: W,
+        progress: Option<DownloadProgressFn>,
+        resume: bool,
+    ) -> Result<u64> {
+        crate::download::download_blob(self, blob_id, writer, progress, resume)
+    }
+
+    /// Check connectivity, credentials and clock skew in one call
+    ///
+    /// Sends one authenticated request and reports whether the host was
+    /// reached, whether its TLS certificate is valid, whether the
+    /// credentials were accepted, the latency, and the difference between
+    /// the local and server clocks. Never fails: problems are part of the
+    /// [`HealthReport`].
+    pub fn health(&self) -> HealthReport {
+        crate::health::check(self)
+    }
+
+    /// Open a connection to the configured host ahead of the first request
+    ///
+    /// Sends a `HEAD` request so the TCP connection and TLS session are set
+    /// up and kept in the connection pool, sparing the first real request the
+    /// setup cost (useful on cold starts). Best-effort: any HTTP status counts
+    /// as success, and only failing to connect within the connect timeout
+    /// ([`Config::connect_timeout`], capped by [`Config::request_deadline`])
+    /// is an error.
+    pub fn warmup(&self) -> Result<()> {
+        let connect_timeout = self.config.connect_timeout();
+        let timeout = self
+            .config
+            .request_deadline()
+            .map_or(connect_timeout, |budget| budget.min(connect_timeout));
+
+        let mut request =
+            HttpRequest::new("HEAD", format!("{}/_special/rest/", self.config.base_url()));
+        request.timeout = timeout;
+        request.connect_timeout = timeout;
+        request
+            .headers
+            .push(("Sec-Rest-Client".to_string(), PROTOCOL_VERSION.to_string()));
+
+        self.transport
+            .send(request)
+            .map(|_| ())
+            .map_err(|e| self.correlate(e))
+    }
+
+    /// Whether the configured host presents a valid TLS certificate, see
+    /// [`Transport::check_tls`].
+    pub(crate) fn check_tls(&self) -> Option<bool> {
+        self.transport
+            .check_tls(&self.config.base_url(), self.config.connect_timeout())
+    }
+
+    /// Send a request, retrying it as allowed by the retry policy.
+    fn request_retrying(
+        &self,
+        path: &str,
+        method: &str,
+        payload: Payload<'_>,
+        deadline: Option<Instant>,
+    ) -> Result<Response> {
+        let Some(policy) = &self.retry_policy else {
+            return self.request_inner(path, method, payload, true, deadline);
+        };
+        let keyed = self
+            .headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case(IDEMPOTENCY_KEY));
+        let mut attempt = 1;
+        loop {
+            let err = match self.request_inner(path, method, payload, true, deadline) {
+                Ok(response) => return Ok(response),
+                Err(err) => err,
+            };
+            if !policy.should_retry(method, keyed, &err) {
+                return Err(err);
+            }
+            let backoff = policy.delay(&err, attempt);
+            let late = deadline.is_some_and(|d| {
+                Instant::now()
+                    .checked_add(backoff)
+                    .is_none_or(|resume| resume >= d)
+            });
+            if attempt > policy.max_retries() || late {
+                self.report_retry(&RetryEvent::new(path, attempt, &err, RetryOutcome::GiveUp));
+                return Err(err);
+            }
+            let outcome = RetryOutcome::Retry { backoff };
+            self.report_retry(&RetryEvent::new(path, attempt, &err, outcome));
+            std::thread::sleep(backoff);
+            attempt += 1;
+        }
+    }
+
+    /// Inner request implementation.
+    ///
+    /// `allow_renew` guards token renewal so an expired token triggers exactly
+    /// one retry. Each attempt's timeout is capped to what is left before
+    /// `deadline`.
+    fn request_inner(
+        &self,
+        path: &str,
+        method: &str,
+        payload: Payload<'_>,
+        allow_renew: bool,
+        deadline: Option<Instant>,
+    ) -> Result<Response> {
+        validate_path(path)?;
+        let timeout = time_left(deadline, self.config.timeout())?;
+
+        let cached = match (&self.cache, payload) {
+            (Some(cache), Payload::Params(params)) if method == "GET" => {
+                let names = self.param_names();
+                let key = cache_key(
+                    method,
+                    &self.config.base_url(),
+                    path,
+                    params,
+                    &self.auth_identity(),
+                    &names,
+                );
+                if let Some(mut response) = cache.get(&key) {
+                    response.from_cache = true;
+                    return Ok(response);
+                }
+                Some((cache, key))
+            }
+            _ => None,
+        };
+
+        if allow_renew {
+            self.renew_if_expired(deadline)?;
+        }
+
+        let (request, current_token) = self.build_request(path, method, payload, timeout)?;
+
+        // Execute request
+        let start = std::time::Instant::now();
+        let http_response = self.transport.send(request).map_err(|e| {
+            // A timeout caused by the deadline cap reports as the deadline.
+            if deadline.is_some_and(|d| Instant::now() >= d) {
+                RestError::DeadlineExceeded
+            } else {
+                e
+            }
+        })?;
+        let status = http_response.status;
+
+        // Get X-Request-Id header
+        let request_id = http_response.header("X-Request-Id").map(|s| s.to_string());
+
+        // Rate limited responses carry the server's delay with their error.
+        let retry_after = match status {
+            429 => http_response
+                .header("Retry-After")
+                .and_then(parse_retry_after),
+            _ => None,
+        };
+        let rate_limited = |err: RestError| match retry_after {
+            Some(retry_after) => RestError::RateLimited {
+                retry_after,
+                source: Box::new(err),
+            },
+            None => err,
+        };
+
+        let body = http_response.body;
+
+        if self.config.debug() {
+            let duration = start.elapsed();
+            eprintln!(
+                "[rest] {} {} => {:?} (status: {})",
                 method, path, duration, status
             );
         }
 
-        // Parse response
-        let mut response: Response = serde_json::from_slice(&body).map_err(|e| {
+        // Parse response; raw endpoints answer without the envelope.
+        let (mut response, raw) = Response::parse_body(&body).map_err(|e| {
             if !(200..400).contains(&status) {
-                RestError::http(
+                rate_limited(RestError::http(
                     status,
                     String::from_utf8_lossy(&body).to_string(),
                     Some(Box::new(e)),
-                )
+                ))
             } else {
                 RestError::Json(e)
             }
         })?;
+        if raw && !(200..300).contains(&status) {
+            return Err(rate_limited(RestError::http(
+                status,
+                String::from_utf8_lossy(&body).to_string(),
+                None,
+            )));
+        }
 
         response.request_id = request_id;
+        response.status = status;
 
-        // Check for token expiration and renew if needed
+        // Check for token expiration and renew if needed. Access-only tokens
+        // (see `Token::from_bearer`) are never renewed.
+        let renewable = current_token.filter(|t| t.has_refresh_token() || t.has_client_id());
         if allow_renew {
-            if let Some(token) = current_token {
+            if let Some(token) = renewable {
                 if response.token.as_deref() == Some("invalid_request_token")
                     && response.extra.as_deref() == Some("token_expired")
                 {
@@ -270,11 +1237,22 @@ impl Client {
                     }
 
                     // Renew and persist the new token so later calls reuse it.
-                    let renewed = self.renew_token(&token)?;
+                    // A renewal that cannot reach the server reports the
+                    // expiry along with the connection failure.
+                    let renewed = match self.renew_token(&token, deadline) {
+                        Ok(renewed) => renewed,
+                        Err(e @ RestError::Transport(_)) if !is_status(&e) => {
+                            return Err(RestError::RenewalUnreachable {
+                                expired: Box::new(RestError::from_response(response)),
+                                source: Box::new(e),
+                            });
+                        }
+                        Err(e) => return Err(e),
+                    };
                     *self.token.lock().unwrap() = Some(renewed);
 
                     // Retry the request once with the renewed token.
-                    return self.request_inner(path, method, param_json, false);
+                    return self.request_inner(path, method, payload, false, deadline);
                 }
             }
         }
@@ -289,14 +1267,196 @@ impl Client {
 
         // Check for error response
         if response.result == "error" {
-            return Err(RestError::from_response(response));
+            return Err(rate_limited(RestError::from_response(response)));
+        }
+
+        #[cfg(feature = "schema-validation")]
+        self.validate_response(&response)?;
+
+        if let Some((cache, key)) = cached {
+            cache.put(&key, &response);
         }
 
         Ok(response)
     }
 
+    /// Renew the token before sending if it is already known to be expired,
+    /// saving the round trip to the server's `token_expired` error.
+    fn renew_if_expired(&self, deadline: Option<Instant>) -> Result<()> {
+        if self.api_key.is_some() && !self.combined_auth {
+            return Ok(());
+        }
+        let token = self.token.lock().unwrap().clone();
+        let Some(token) =
+            token.filter(|t| t.is_expired() && t.has_refresh_token() && t.has_client_id())
+        else {
+            return Ok(());
+        };
+        if self.config.debug() {
+            eprintln!("[rest] Token expired, renewing before the request");
+        }
+        let renewed = self.renew_token(&token, deadline)?;
+        *self.token.lock().unwrap() = Some(renewed);
+        Ok(())
+    }
+
+    /// Build the HTTP request for a REST call, returning it with the token
+    /// it is authenticated with.
+    fn build_request(
+        &self,
+        path: &str,
+        method: &str,
+        payload: Payload<'_>,
+        timeout: Duration,
+    ) -> Result<(HttpRequest, Option<Token>)> {
+        // Build base URL
+        let base_url = self.config.base_url();
+        let url = format!("{}/_special/rest/{}", base_url, path);
+
+        let mut query_params: Vec<(String, String)> = Vec::new();
+        let mut body_bytes: Vec<u8> = Vec::new();
+        let mut content_type = "application/json";
+
+        // Add `noraw` to object parameters when configured.
+        let with_noraw;
+        let payload = match payload {
+            Payload::Params(serde_json::Value::Object(map)) if self.noraw => {
+                let mut map = map.clone();
+                map.insert("noraw".to_string(), "true".into());
+                with_noraw = serde_json::Value::Object(map);
+                Payload::Params(&with_noraw)
+            }
+            other => other,
+        };
+
+        match (method, payload) {
+            (
+                "PUT" | "POST" | "PATCH",
+                Payload::Raw {
+                    body,
+                    content_type: ct,
+                },
+            ) => {
+                body_bytes = body.to_vec();
+                content_type = ct;
+            }
+            (_, Payload::Raw { .. }) => {
+                return Err(RestError::RequestBuild(format!(
+                    "{} requests cannot carry a body",
+                    method
+                )))
+            }
+            (_, Payload::Params(param_json)) => match method {
+                "GET" | "HEAD" | "OPTIONS" => match (self.query_encoding, param_json) {
+                    (QueryEncoding::Repeated, serde_json::Value::Object(map)) => {
+                        repeated_query(map, &mut query_params);
+                    }
+                    _ => {
+                        // Parameters go in query string
+                        let param_str = serde_json::to_string(param_json)?;
+                        query_params.push(("_".to_string(), param_str));
+                    }
+                },
+                "PUT" | "POST" | "PATCH" => {
+                    // Parameters go in request body
+                    body_bytes = serde_json::to_vec(param_json)?;
+                }
+                "DELETE" => {
+                    // No parameters
+                }
+                _ => {
+                    return Err(RestError::RequestBuild(format!(
+                        "Unsupported HTTP method: {}",
+                        method
+                    )))
+                }
+            },
+        }
+
+        // Apply API key authentication if present
+        if let Some(ref api_key) = self.api_key {
+            api_key.apply_params(method, path, &mut query_params, &body_bytes)?;
+        }
+
+        // Build the full URL with an (optional) query string.
+        let full_url = if query_params.is_empty() {
+            url
+        } else {
+            let query = form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(&query_params)
+                .finish();
+            format!("{}?{}", url, query)
+        };
+
+        // Snapshot the current token (used only when not authenticating by
+        // key, unless both were requested together).
+        let current_token = if self.api_key.is_none() || self.combined_auth {
+            self.token.lock().unwrap().clone()
+        } else {
+            None
+        };
+
+        // Build the request.
+        let mut request = HttpRequest::new(method, full_url);
+        request.param_names = self.param_names();
+        request.timeout = timeout;
+        request.connect_timeout = self.config.connect_timeout().min(timeout);
+        let headers = &mut request.headers;
+        headers.push(("Sec-Rest-Http".to_string(), "false".to_string()));
+        headers.push(("Sec-Rest-Client".to_string(), PROTOCOL_VERSION.to_string()));
+
+        // Headers the client sets for this request; custom headers of the
+        // same name are dropped.
+        let mut managed = vec!["Sec-Rest-Http", "Sec-Rest-Client"];
+        if self.correlation_id.is_some() {
+            managed.push("X-Correlation-Id");
+        }
+        if current_token.is_some() {
+            managed.push("Authorization");
+        }
+        if !body_bytes.is_empty() {
+            managed.push("Content-Type");
+        }
+
+        if let Some(user_agent) = self.config.user_agent() {
+            let custom = self
+                .headers
+                .iter()
+                .any(|(k, _)| k.eq_ignore_ascii_case("User-Agent"));
+            if !custom {
+                headers.push(("User-Agent".to_string(), user_agent.to_string()));
+            }
+        }
+
+        // Apply user-supplied custom headers before the client-managed ones.
+        headers.extend(
+            self.headers
+                .iter()
+                .filter(|(k, _)| !managed.iter().any(|m| m.eq_ignore_ascii_case(k)))
+                .cloned(),
+        );
+
+        if let Some(ref id) = self.correlation_id {
+            headers.push(("X-Correlation-Id".to_string(), id.clone()));
+        }
+
+        if let Some(ref token) = current_token {
+            headers.push((
+                "Authorization".to_string(),
+                format!("Bearer {}", token.access_token),
+            ));
+        }
+
+        if !body_bytes.is_empty() {
+            headers.push(("Content-Type".to_string(), content_type.to_string()));
+            request.body = body_bytes;
+        }
+
+        Ok((request, current_token))
+    }
+
     /// Renew an expired token, returning the renewed token.
-    fn renew_token(&self, token: &Token) -> Result<Token> {
+    fn renew_token(&self, token: &Token, deadline: Option<Instant>) -> Result<Token> {
         if !token.has_client_id() {
             return Err(RestError::NoClientId);
         }
@@ -311,18 +1471,36 @@ impl Client {
             token: Arc::new(Mutex::new(None)),
             api_key: None,
             headers: self.headers.clone(),
+            correlation_id: self.correlation_id.clone(),
+            transport: self.transport.clone(),
+            noraw: true,
+            combined_auth: false,
+            query_encoding: QueryEncoding::default(),
+            cache: None,
+            retry_hook: None,
+            retry_policy: None,
+            #[cfg(feature = "schema-validation")]
+            validator: None,
         };
 
         let mut params = HashMap::new();
         params.insert("grant_type", "refresh_token");
         params.insert("client_id", &token.client_id);
         params.insert("refresh_token", &token.refresh_token);
-        params.insert("noraw", "true");
 
-        let mut renewed: Token = ctx.apply("OAuth2:token", "POST", params)?;
+        let params = serde_json::to_value(params)?;
+        let mut renewed: Token = ctx
+            .request_inner(
+                "OAuth2:token",
+                "POST",
+                Payload::Params(&params),
+                false,
+                deadline,
+            )?
+            .take_apply()?;
 
         // The renewal response does not echo the client_id; carry it over so
-        // the token remains renewable.
+        // the token remains renewable. Its `issued_at` is now (serde default).
         renewed.client_id = token.client_id.clone();
 
         Ok(renewed)
@@ -335,6 +1513,138 @@ impl Default for Client {
     }
 }
 
+impl std::fmt::Debug for Client {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let token = self.token.lock().unwrap().as_ref().map(|_| "<redacted>");
+        let headers: Vec<(&str, &str)> = self
+            .headers
+            .iter()
+            .map(|(k, _)| (k.as_str(), "<redacted>"))
+            .collect();
+        f.debug_struct("Client")
+            .field("config", &self.config)
+            .field("token", &token)
+            .field("api_key", &self.api_key)
+            .field("headers", &headers)
+            .field("correlation_id", &self.correlation_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Check that `path` is a well-formed API path, such as
+/// `Module/SubModule:method` or `User/usr-123`.
+///
+/// Catches obvious typos before any network call: an empty path, a leading,
+/// trailing or doubled `/`, whitespace or control characters, `?` or `#`,
+/// and an empty or repeated `:method` suffix. Returns
+/// [`RestError::RequestBuild`] describing the problem. Called by every
+/// request; it does not check that the endpoint exists.
+pub fn validate_path(path: &str) -> Result<()> {
+    let invalid = |reason: &str| {
+        Err(RestError::RequestBuild(format!(
+            "invalid API path {:?}: {}",
+            path, reason
+        )))
+    };
+
+    if path.is_empty() {
+        return invalid("path is empty");
+    }
+    if let Some(c) = path
+        .chars()
+        .find(|c| c.is_whitespace() || c.is_control() || matches!(c, '?' | '#'))
+    {
+        return invalid(&format!("unexpected character {:?}", c));
+    }
+    if path.starts_with('/') || path.ends_with('/') {
+        return invalid("leading or trailing '/'");
+    }
+    if path.contains("//") {
+        return invalid("empty segment ('//')");
+    }
+    let (resource, method) = match path.split_once(':') {
+        Some((resource, method)) => (resource, Some(method)),
+        None => (path, None),
+    };
+    if resource.is_empty() || resource.ends_with('/') {
+        return invalid("missing resource before ':'");
+    }
+    if let Some(method) = method {
+        if method.is_empty() || method.contains([':', '/']) {
+            return invalid("method after ':' must be a single non-empty name");
+        }
+    }
+    Ok(())
+}
+
+/// Whether `err` is an HTTP status reported by the transport, as opposed to a
+/// failure to exchange anything with the server.
+fn is_status(err: &RestError) -> bool {
+    matches!(err, RestError::Transport(rsurl::Error::Status { .. }))
+}
+
+/// What a request carries: JSON parameters, or a raw body.
+#[derive(Clone, Copy)]
+enum Payload<'a> {
+    /// Parameters, sent as JSON in the body or the `_` query parameter
+    /// depending on the method
+    Params(&'a serde_json::Value),
+    /// A body sent as-is
+    Raw {
+        body: &'a [u8],
+        content_type: &'a str,
+    },
+}
+
+/// Time left before `deadline`, capped to `max`.
+///
+/// Fails with [`RestError::DeadlineExceeded`] once the deadline has passed.
+fn time_left(deadline: Option<Instant>, max: Duration) -> Result<Duration> {
+    match deadline {
+        None => Ok(max),
+        Some(d) => match d.checked_duration_since(Instant::now()) {
+            Some(left) if !left.is_zero() => Ok(left.min(max)),
+            _ => Err(RestError::DeadlineExceeded),
+        },
+    }
+}
+
+/// Parse a `Retry-After` header: a number of seconds, or an HTTP date
+/// (zero once past).
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let left = date.signed_duration_since(chrono::Utc::now());
+    Some(left.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Append the query pairs of `params` in the [`QueryEncoding::Repeated`]
+/// form.
+fn repeated_query(
+    params: &serde_json::Map<String, serde_json::Value>,
+    query: &mut Vec<(String, String)>,
+) {
+    fn text(value: &serde_json::Value) -> String {
+        match value {
+            serde_json::Value::String(s) => s.clone(),
+            serde_json::Value::Null => String::new(),
+            other => other.to_string(),
+        }
+    }
+
+    for (name, value) in params {
+        match value {
+            serde_json::Value::Array(items) => {
+                query.extend(items.iter().map(|item| (name.clone(), text(item))));
+            }
+            other => query.push((name.clone(), text(other))),
+        }
+    }
+}
+
 /// Deprecated alias for [`Client`].
 ///
 /// The type was renamed to [`Client`] to better match Rust conventions; this
@@ -365,6 +1675,625 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cache::DiskCache;
+    use crate::transport::testing::{Capture, Fixed};
+    use crate::transport::HttpResponse;
+
+    #[test]
+    fn test_do_request_body() {
+        let capture = Arc::new(Capture::default());
+        let ctx = Client::new().with_transport(capture.clone());
+
+        let doc = b"<html>hi</html>".as_slice();
+        ctx.do_request_body("Doc:render", "POST", doc, "text/html", Some(15))
+            .unwrap();
+        let sent = capture.0.lock().unwrap().pop().unwrap();
+        assert_eq!(sent.body, b"<html>hi</html>");
+        assert_eq!(sent.header("Content-Type"), Some("text/html"));
+
+        let err = ctx
+            .do_request_body("Doc:render", "POST", doc, "text/html", Some(3))
+            .unwrap_err();
+        assert!(matches!(err, RestError::RequestBuild(_)));
+        let err = ctx
+            .do_request_body("Doc:render", "GET", doc, "text/html", None)
+            .unwrap_err();
+        assert!(matches!(err, RestError::RequestBuild(_)));
+        assert!(capture.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_http_status() {
+        let status = |code| {
+            Client::new()
+                .with_transport(Arc::new(Fixed(code, r#"{"result":"success","data":{}}"#)))
+                .do_request("Job", "POST", ())
+                .unwrap()
+                .http_status()
+        };
+        assert_eq!(status(200), 200);
+        assert_eq!(status(201), 201);
+        assert_eq!(status(202), 202);
+        assert_eq!(Response::from_raw(serde_json::Value::Null).http_status(), 0);
+    }
+
+    #[test]
+    fn test_validate_path() {
+        for ok in [
+            "Misc/Debug:fixedString",
+            "User",
+            "User/usr-123",
+            "Cloud/Aws/Bucket/Upload/cabu-1:signV4",
+            "OAuth2:token",
+        ] {
+            assert!(validate_path(ok).is_ok(), "{}", ok);
+        }
+        for bad in [
+            "",
+            "/User",
+            "User/",
+            "Misc//Debug",
+            "Misc/Debug :fixedString",
+            "User?x=1",
+            ":token",
+            "User/:get",
+            "User:",
+            "User:a:b",
+            "User:a/b",
+        ] {
+            assert!(
+                matches!(validate_path(bad), Err(RestError::RequestBuild(_))),
+                "{:?}",
+                bad
+            );
+        }
+
+        // Rejected before the request is sent.
+        let capture = Arc::new(Capture::default());
+        let ctx = Client::new().with_transport(capture.clone());
+        assert!(ctx.do_request("Misc//Debug", "GET", ()).is_err());
+        assert!(capture.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_warmup() {
+        let capture = Arc::new(Capture::default());
+        let ctx =
+            Client::with_config(Config::for_host("example.com")).with_transport(capture.clone());
+        ctx.warmup().unwrap();
+
+        let sent = capture.0.lock().unwrap().pop().unwrap();
+        assert_eq!(sent.method, "HEAD");
+        assert_eq!(sent.url, "https://example.com/_special/rest/");
+        assert_eq!(sent.connect_timeout, Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_repeated_query_encoding() {
+        let capture = Arc::new(Capture::default());
+        let ctx = Client::new()
+            .with_transport(capture.clone())
+            .with_query_encoding(QueryEncoding::Repeated);
+        ctx.do_request(
+            "Data/List",
+            "GET",
+            serde_json::json!({"ids": [1, 2], "q": "a b", "none": null, "empty": [], "f": {"x": 1}}),
+        )
+        .unwrap();
+        let sent = capture.0.lock().unwrap().pop().unwrap();
+        let query = sent.url.split_once('?').unwrap().1;
+        let mut pairs: Vec<(String, String)> = form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+        pairs.sort();
+        let pair = |k: &str, v: &str| (k.to_string(), v.to_string());
+        assert_eq!(
+            pairs,
+            [
+                pair("f", r#"{"x":1}"#),
+                pair("ids", "1"),
+                pair("ids", "2"),
+                pair("none", ""),
+                pair("q", "a b"),
+            ]
+        );
+
+        // Non-object parameters, and bodies, keep the JSON form.
+        ctx.do_request("Data/List", "GET", [1, 2]).unwrap();
+        let sent = capture.0.lock().unwrap().pop().unwrap();
+        assert!(sent.url.ends_with("?_=%5B1%2C2%5D"), "{}", sent.url);
+        ctx.do_request("Data/List", "POST", serde_json::json!({"ids": [1]}))
+            .unwrap();
+        let sent = capture.0.lock().unwrap().pop().unwrap();
+        assert_eq!(sent.body, br#"{"ids":[1]}"#);
+    }
+
+    #[test]
+    fn test_get_and_post() {
+        let capture = Arc::new(Capture::default());
+        let ctx = Client::new().with_transport(capture.clone());
+
+        let _: serde_json::Value = ctx.get("Misc/Debug:serverTime").unwrap();
+        let sent = capture.0.lock().unwrap().pop().unwrap();
+        assert_eq!(sent.method, "GET");
+        assert!(sent.url.ends_with("?_=%7B%7D"), "{}", sent.url);
+
+        let _: serde_json::Value = ctx
+            .post("Data/List", serde_json::json!({"ids": [1]}))
+            .unwrap();
+        let sent = capture.0.lock().unwrap().pop().unwrap();
+        assert_eq!(sent.method, "POST");
+        assert_eq!(sent.body, br#"{"ids":[1]}"#);
+
+        let ctx = Client::new().with_transport(Arc::new(Fixed(
+            200,
+            r#"{"result":"success","data":{"id":7}}"#,
+        )));
+        let data: HashMap<String, i64> = ctx.get("Data/Item").unwrap();
+        assert_eq!(data["id"], 7);
+    }
+
+    #[test]
+    fn test_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let capture = Arc::new(Capture::default());
+        let ctx = Client::new()
+            .with_transport(capture.clone())
+            .with_cache(Arc::new(DiskCache::new(
+                dir.path(),
+                Duration::from_secs(60),
+            )));
+        let count = || capture.0.lock().unwrap().len();
+
+        let first = ctx
+            .do_request("Data/List", "GET", serde_json::json!({"a": 1}))
+            .unwrap();
+        assert!(!first.from_cache);
+        let second = ctx
+            .do_request("Data/List", "GET", serde_json::json!({"a": 1}))
+            .unwrap();
+        assert!(second.from_cache);
+        assert_eq!(count(), 1);
+
+        // Other parameters and methods go to the network.
+        ctx.do_request("Data/List", "GET", serde_json::json!({"a": 2}))
+            .unwrap();
+        ctx.do_request("Data/List", "POST", serde_json::json!({"a": 1}))
+            .unwrap();
+        ctx.do_request("Data/List", "POST", serde_json::json!({"a": 1}))
+            .unwrap();
+        assert_eq!(count(), 4);
+
+        // Another account sharing the cache directory gets its own entries.
+        let other = ctx
+            .clone()
+            .with_token(Token::from_bearer("Bearer other").unwrap());
+        let response = other
+            .do_request("Data/List", "GET", serde_json::json!({"a": 1}))
+            .unwrap();
+        assert!(!response.from_cache);
+        assert_eq!(count(), 5);
+
+        // Errors are not cached.
+        let ctx = Client::new()
+            .with_transport(Arc::new(Fixed(500, r#"{"result":"error","error":"x"}"#)))
+            .with_cache(Arc::new(DiskCache::new(
+                dir.path(),
+                Duration::from_secs(60),
+            )));
+        assert!(ctx.do_request("Data/Fail", "GET", ()).is_err());
+        assert!(ctx.do_request("Data/Fail", "GET", ()).is_err());
+    }
+
+    #[cfg(feature = "schema-validation")]
+    #[test]
+    fn test_response_validator() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {"id": {"type": "integer"}, "name": {"type": "string"}},
+            "required": ["id"],
+        });
+        let client = |body| {
+            Client::new()
+                .with_transport(Arc::new(Fixed(200, body)))
+                .with_response_validator(&schema)
+                .unwrap()
+        };
+
+        let ok = client(r#"{"result":"success","data":{"id":1,"name":"a"}}"#);
+        assert!(ok.do_request("Data/Item", "GET", ()).is_ok());
+
+        let bad = client(r#"{"result":"success","data":{"name":7}}"#);
+        let err = bad.do_request("Data/Item", "GET", ()).unwrap_err();
+        let RestError::SchemaViolation { errors } = &err else {
+            panic!("expected SchemaViolation, got {:?}", err);
+        };
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(
+            errors.iter().any(|e| e.starts_with("data/name: ")),
+            "{:?}",
+            errors
+        );
+
+        let invalid = serde_json::json!({"type": "no-such-type"});
+        assert!(Client::new().with_response_validator(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_noraw() {
+        let capture = Arc::new(Capture::default());
+        let ctx = Client::new()
+            .with_transport(capture.clone())
+            .with_noraw(true);
+        ctx.do_request("OAuth2:token", "POST", serde_json::json!({"a": 1}))
+            .unwrap();
+        let sent = capture.0.lock().unwrap().pop().unwrap();
+        let params: serde_json::Value = serde_json::from_slice(&sent.body).unwrap();
+        assert_eq!(params, serde_json::json!({"a": 1, "noraw": "true"}));
+    }
+
+    #[test]
+    fn test_raw_response() {
+        let ctx = Client::new().with_transport(Arc::new(Fixed(200, r#"{"access_token":"x"}"#)));
+        let response = ctx.do_request("OAuth2:token", "POST", ()).unwrap();
+        assert_eq!(response.get_string("access_token").as_deref(), Some("x"));
+
+        let ctx = Client::new().with_transport(Arc::new(Fixed(400, r#"{"error":"bad"}"#)));
+        let err = ctx.do_request("OAuth2:token", "POST", ()).unwrap_err();
+        assert_eq!(err.status_code(), Some(400));
+    }
+
+    #[test]
+    fn test_stream_ndjson() {
+        let ctx = Client::new().with_transport(Arc::new(Fixed(200, "{\"id\":1}\n{\"id\":2}\n")));
+        let rows: Vec<serde_json::Value> = ctx
+            .stream_ndjson("Data/Export", "GET", ())
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            [serde_json::json!({"id": 1}), serde_json::json!({"id": 2})]
+        );
+
+        let ctx = Client::new().with_transport(Arc::new(Fixed(
+            403,
+            r#"{"result":"error","error":"denied","code":403,"token":"error_access_denied"}"#,
+        )));
+        let err = ctx.stream_ndjson("Data/Export", "GET", ()).unwrap_err();
+        assert!(matches!(err, RestError::Api { .. }));
+        assert!(err.is_permission_denied());
+    }
+
+    #[test]
+    fn test_redirect_error() {
+        let ctx = Client::new().with_transport(Arc::new(Fixed(
+            200,
+            r#"{"result":"redirect","redirect_url":"/next?page=2","redirect_code":302,"data":{"partial":true}}"#,
+        )));
+        let err = ctx.do_request("Data/List", "GET", ()).unwrap_err();
+        let RestError::Redirect { response, .. } = err else {
+            panic!("expected a redirect, got {:?}", err);
+        };
+        assert_eq!(response.data, Some(serde_json::json!({"partial": true})));
+
+        let ctx = Client::new().with_transport(Arc::new(Fixed(
+            200,
+            r#"{"result":"redirect","exception":"Exception\\Login","redirect_url":"/login"}"#,
+        )));
+        let err = ctx.do_request("Data/List", "GET", ()).unwrap_err();
+        assert!(matches!(err, RestError::LoginRequired));
+    }
+
+    #[test]
+    fn test_access_only_token_not_renewed() {
+        let ctx = Client::new()
+            .with_token(Token::from_bearer("Bearer abc").unwrap())
+            .with_transport(Arc::new(Fixed(
+                401,
+                r#"{"result":"error","error":"expired","code":401,"token":"invalid_request_token","extra":"token_expired"}"#,
+            )));
+        let err = ctx.do_request("User:get", "GET", ()).unwrap_err();
+        assert_eq!(err.auth_error_kind(), Some(crate::AuthErrorKind::Expired));
+    }
+
+    /// Answers with an expired token error, and fails the renewal request as
+    /// if the network were down.
+    struct Offline;
+
+    impl Transport for Offline {
+        fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+            if request.url.contains("OAuth2:token") {
+                return Err(RestError::Transport(rsurl::Error::Io(
+                    std::io::Error::from(std::io::ErrorKind::ConnectionRefused),
+                )));
+            }
+            Ok(HttpResponse::new(
+                401,
+                Vec::new(),
+                br#"{"result":"error","error":"expired","code":401,"token":"invalid_request_token","extra":"token_expired"}"#.to_vec(),
+            ))
+        }
+    }
+
+    /// Answers token renewals with a new token, and other requests with an
+    /// empty success. Records the URL and `Authorization` of each request.
+    #[derive(Default)]
+    struct Renewing(Mutex<Vec<(String, Option<String>)>>);
+
+    impl Transport for Renewing {
+        fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+            let auth = request
+                .headers
+                .iter()
+                .find(|(k, _)| k == "Authorization")
+                .map(|(_, v)| v.clone());
+            self.0.lock().unwrap().push((request.url.clone(), auth));
+            let body = if request.url.contains("OAuth2:token") {
+                r#"{"access_token":"new","refresh_token":"r2","token_type":"Bearer","expires_in":3600}"#
+            } else {
+                r#"{"result":"success"}"#
+            };
+            Ok(HttpResponse::new(200, Vec::new(), body.as_bytes().to_vec()))
+        }
+    }
+
+    #[test]
+    fn test_expired_token_renewed_before_request() {
+        let mut token = Token::new("old".into(), "refresh".into(), "client".into(), 60);
+        token.issued_at -= 120;
+        let transport = Arc::new(Renewing::default());
+        let ctx = Client::new()
+            .with_token(token)
+            .with_transport(transport.clone());
+
+        ctx.do_request("User:get", "GET", ()).unwrap();
+        ctx.do_request("User:get", "GET", ()).unwrap();
+        let requests = transport.0.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].0.contains("OAuth2:token"));
+        for (url, auth) in &requests[1..] {
+            assert!(url.contains("User:get"));
+            assert_eq!(auth.as_deref(), Some("Bearer new"));
+        }
+        let renewed = ctx.token.lock().unwrap().clone().unwrap();
+        assert!(!renewed.is_expired());
+        assert_eq!(renewed.client_id, "client");
+    }
+
+    #[test]
+    fn test_renewal_unreachable() {
+        let token = Token::new("abc".into(), "refresh".into(), "client".into(), 3600);
+        let ctx = Client::new()
+            .with_token(token)
+            .with_transport(Arc::new(Offline));
+        let err = ctx.do_request("User:get", "GET", ()).unwrap_err();
+
+        let RestError::RenewalUnreachable { expired, source } = &err else {
+            panic!("expected RenewalUnreachable, got {:?}", err);
+        };
+        assert_eq!(expired.status_code(), Some(401));
+        assert!(matches!(**source, RestError::Transport(_)));
+        assert_eq!(err.auth_error_kind(), Some(crate::AuthErrorKind::Expired));
+        assert!(err.is_retryable());
+        assert_eq!(err.http_status(), 502);
+        assert!(err.to_string().contains("server unreachable"), "{}", err);
+    }
+
+    #[test]
+    fn test_curl_command() {
+        let key = ApiKey::new("key".to_string(), &"A".repeat(43)).unwrap();
+        let ctx = Client::new().with_api_key(key);
+
+        let command = ctx
+            .curl_command("User:get", "GET", serde_json::json!({"id": 1}), false)
+            .unwrap();
+        assert!(
+            command.starts_with("curl -X GET 'https://www.atonline.com/_special/rest/User:get?")
+        );
+        assert!(command.contains("_sign="));
+        assert!(command.contains("-H 'Sec-Rest-Client: "));
+
+        let command = ctx
+            .curl_command("User", "POST", serde_json::json!({"a": 1}), true)
+            .unwrap();
+        assert!(command.contains("_sign=%5Bredacted%5D"));
+        assert!(command.ends_with(r#"--data-binary '{"a":1}'"#));
+
+        assert!(ctx.curl_command("/User", "GET", (), false).is_err());
+    }
+
+    #[test]
+    fn test_method_dependent_signing() {
+        let key = ApiKey::new("key".to_string(), &"A".repeat(43)).unwrap();
+        let capture = Arc::new(Capture::default());
+        let ctx = Client::new()
+            .with_api_key(key.clone())
+            .with_transport(capture.clone());
+        let params = serde_json::json!({"a": 1});
+        let empty_hash = purecrypto::hash::sha256(b"").to_vec();
+
+        // GET: the parameters are in the signed query, the body is empty.
+        let input = ctx
+            .signing_input("Data/List", "GET", &params)
+            .unwrap()
+            .unwrap();
+        let names: Vec<&str> = input
+            .query
+            .split('&')
+            .map(|p| p.split('=').next().unwrap())
+            .collect();
+        assert_eq!(names, ["_", "_key", "_nonce", "_time"]);
+        assert!(
+            input.query.starts_with("_=%7B%22a%22%3A1%7D&_key=key&"),
+            "{}",
+            input.query
+        );
+        assert_eq!(input.body_sha256, empty_hash);
+
+        // POST: the query only has the auth parameters; the body hash covers
+        // the parameters.
+        let input = ctx
+            .signing_input("Data/List", "POST", &params)
+            .unwrap()
+            .unwrap();
+        let names: Vec<&str> = input
+            .query
+            .split('&')
+            .map(|p| p.split('=').next().unwrap())
+            .collect();
+        assert_eq!(names, ["_key", "_nonce", "_time"]);
+        assert_eq!(
+            input.body_sha256,
+            purecrypto::hash::sha256(br#"{"a":1}"#).to_vec()
+        );
+        assert!(input
+            .to_string()
+            .contains("method: POST\npath: Data/List\n"));
+
+        // The signature sent is the one over that input.
+        for method in ["GET", "POST"] {
+            ctx.do_request("Data/List", method, &params).unwrap();
+            let sent = capture.0.lock().unwrap().pop().unwrap();
+            let query = sent.url.split_once('?').unwrap().1;
+            let pairs: HashMap<String, String> = form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect();
+            let expected = key
+                .generate_signature(method, "Data/List", &pairs, &sent.body)
+                .unwrap();
+            assert_eq!(pairs["_sign"], expected, "{}", method);
+        }
+
+        assert!(Client::new()
+            .signing_input("Data/List", "GET", ())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_custom_signing_param_names() {
+        let names = ParamNames {
+            key: "k".to_string(),
+            time: "t".to_string(),
+            nonce: "n".to_string(),
+            sign: "s".to_string(),
+        };
+        let key = ApiKey::new("key".to_string(), &"A".repeat(43))
+            .unwrap()
+            .with_param_names(names.clone());
+        let capture = Arc::new(Capture::default());
+        let ctx = Client::new()
+            .with_api_key(key.clone())
+            .with_transport(capture.clone());
+
+        ctx.do_request("Data/List", "GET", serde_json::json!({"a": 1}))
+            .unwrap();
+        let sent = capture.0.lock().unwrap().pop().unwrap();
+        let query = sent.url.split_once('?').unwrap().1;
+        let pairs: HashMap<String, String> = form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+        let mut sent_names: Vec<&str> = pairs.keys().map(String::as_str).collect();
+        sent_names.sort();
+        assert_eq!(sent_names, ["_", "k", "n", "s", "t"]);
+        assert_eq!(pairs["k"], "key");
+
+        // The signature covers the renamed parameters, not itself.
+        let expected = key
+            .generate_signature("GET", "Data/List", &pairs, b"")
+            .unwrap();
+        assert_eq!(pairs["s"], expected);
+        let input = ctx
+            .signing_input("Data/List", "GET", serde_json::json!({"a": 1}))
+            .unwrap()
+            .unwrap();
+        assert!(!input.query.contains("s="), "{}", input.query);
+
+        // Traces and curl commands mask the renamed signature.
+        let (_, trace) = ctx.do_request_traced("Data/List", "GET", ());
+        assert!(trace.redacted().url.contains("&s=%5Bredacted%5D"));
+        let command = ctx.curl_command("Data/List", "GET", (), true).unwrap();
+        assert!(command.contains("&s=%5Bredacted%5D"), "{}", command);
+
+        // sign_request signs under the same names.
+        let signed = key
+            .sign_request("GET", "Data/List", &HashMap::new(), b"")
+            .unwrap();
+        let pairs: HashMap<String, String> = signed
+            .pairs()
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        assert_eq!(
+            key.generate_signature("GET", "Data/List", &pairs, b"")
+                .unwrap(),
+            signed.sign
+        );
+    }
+
+    #[test]
+    fn test_do_request_traced() {
+        let ctx = Client::new()
+            .with_token(Token::new(
+                "access".to_string(),
+                "refresh".to_string(),
+                "client".to_string(),
+                3600,
+            ))
+            .with_transport(Arc::new(Fixed(200, r#"{"result":"success","data":1}"#)));
+        let (result, trace) = ctx.do_request_traced("Thing", "POST", serde_json::json!({"a": 1}));
+
+        assert!(result.is_ok());
+        assert_eq!(trace.method, "POST");
+        assert_eq!(trace.url, "https://www.atonline.com/_special/rest/Thing");
+        assert_eq!(trace.request_body, br#"{"a":1}"#);
+        assert_eq!(trace.status, Some(200));
+        assert_eq!(trace.response_body, br#"{"result":"success","data":1}"#);
+        let auth = |t: &RequestTrace| {
+            t.request_headers
+                .iter()
+                .find(|(k, _)| k == "Authorization")
+                .map(|(_, v)| v.clone())
+        };
+        assert_eq!(auth(&trace).as_deref(), Some("Bearer access"));
+        assert_eq!(auth(&trace.redacted()).as_deref(), Some("[redacted]"));
+
+        // Requests rejected before sending leave an empty trace.
+        let (result, trace) = ctx.do_request_traced("", "GET", ());
+        assert!(result.is_err());
+        assert_eq!(trace, RequestTrace::default());
+    }
+
+    #[test]
+    fn test_clone_shares_state() {
+        fn assert_shareable<T: Clone + Send + Sync>() {}
+        assert_shareable::<Client>();
+        assert_shareable::<Config>();
+
+        let transport: Arc<dyn Transport> = Arc::new(Capture::default());
+        let ctx = Client::with_config(Config::new(
+            "http".to_string(),
+            "localhost:8080".to_string(),
+        ))
+        .with_token(Token::new(
+            "access".to_string(),
+            "refresh".to_string(),
+            "client".to_string(),
+            3600,
+        ))
+        .with_header("X-Test", "1")
+        .with_correlation_id("corr")
+        .with_noraw(true)
+        .with_transport(transport.clone());
+        let clone = ctx.clone();
+
+        assert!(Arc::ptr_eq(&ctx.transport, &clone.transport));
+        assert!(Arc::ptr_eq(&ctx.token, &clone.token));
+        assert_eq!(clone.headers(), ctx.headers());
+        assert_eq!(clone.correlation_id(), Some("corr"));
+        assert!(clone.noraw);
+    }
 
     #[test]
     fn test_rest_context_creation() {
@@ -381,6 +2310,125 @@ mod tests {
         assert_eq!(ctx.config().host(), "localhost:8080");
     }
 
+    #[test]
+    fn test_deadline_exceeded() {
+        // A deadline already in the past fails before any network access.
+        let ctx = Client::new();
+        let result = ctx.do_request_deadline("Misc/Debug:fixedString", "GET", (), Instant::now());
+        assert!(matches!(result, Err(RestError::DeadlineExceeded)));
+
+        let far = Instant::now() + Duration::from_secs(3600);
+        let max = Duration::from_secs(300);
+        assert_eq!(time_left(Some(far), max).unwrap(), max);
+        assert!(time_left(Some(far), Duration::MAX).unwrap() <= Duration::from_secs(3600));
+        assert_eq!(time_left(None, max).unwrap(), max);
+
+        // A budget past what an Instant can hold is no deadline.
+        let ctx = Client::with_config(Config::default().with_request_deadline(Duration::MAX))
+            .with_transport(Arc::new(Capture::default()));
+        ctx.do_request("User:get", "GET", ()).unwrap();
+    }
+
+    #[test]
+    fn test_user_agent() {
+        let capture = Arc::new(Capture::default());
+        let user_agent = |request: &HttpRequest| {
+            let values: Vec<&str> = request
+                .headers
+                .iter()
+                .filter(|(k, _)| k == "User-Agent")
+                .map(|(_, v)| v.as_str())
+                .collect();
+            values.join(",")
+        };
+
+        let ctx = Client::new().with_transport(capture.clone());
+        ctx.do_request("Thing", "GET", ()).unwrap();
+        let ctx = ctx.with_user_agent("my-app/1.2");
+        ctx.do_request("Thing", "GET", ()).unwrap();
+        let ctx = ctx.with_header("User-Agent", "custom");
+        ctx.do_request("Thing", "GET", ()).unwrap();
+
+        let requests = capture.0.lock().unwrap();
+        let sent: Vec<String> = requests.iter().map(user_agent).collect();
+        assert_eq!(sent, ["", "my-app/1.2", "custom"]);
+    }
+
+    #[test]
+    fn test_do_request_with_timeout() {
+        let capture = Arc::new(Capture::default());
+        let ctx = Client::new().with_transport(capture.clone());
+
+        ctx.do_request_with_timeout("Report:slow", "GET", (), Duration::from_secs(600))
+            .unwrap();
+        ctx.do_request("Report:fast", "GET", ()).unwrap();
+
+        let requests = capture.0.lock().unwrap();
+        assert_eq!(requests[0].timeout, Duration::from_secs(600));
+        assert_eq!(requests[1].timeout, ctx.config().timeout());
+    }
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let token = Token::new(
+            "secret-access".to_string(),
+            "secret-refresh".to_string(),
+            "client-1".to_string(),
+            3600,
+        );
+        let key = ApiKey::new("key-123".to_string(), &"A".repeat(43)).unwrap();
+        let ctx = Client::new()
+            .with_token(token)
+            .with_api_key(key)
+            .with_header("X-Secret", "hunter2");
+
+        for dump in [ctx.debug_summary(), format!("{:?}", ctx)] {
+            assert!(dump.contains("key-123"), "{}", dump);
+            assert!(dump.contains("X-Secret"), "{}", dump);
+            assert!(!dump.contains("secret-access"), "{}", dump);
+            assert!(!dump.contains("secret-refresh"), "{}", dump);
+            assert!(!dump.contains("hunter2"), "{}", dump);
+        }
+        assert!(ctx.debug_summary().contains("auth: api_key"));
+        assert!(Client::new().debug_summary().contains("auth: none"));
+    }
+
+    #[test]
+    fn test_correlation_id() {
+        let ctx = Client::new();
+        assert_eq!(ctx.correlation_id(), None);
+        assert!(matches!(
+            ctx.correlate(RestError::LoginRequired),
+            RestError::LoginRequired
+        ));
+
+        let ctx = ctx.with_correlation_id("trace-1");
+        assert_eq!(ctx.correlation_id(), Some("trace-1"));
+        let err = ctx.correlate(RestError::LoginRequired);
+        assert_eq!(err.correlation_id(), Some("trace-1"));
+        assert!(matches!(err.inner(), RestError::LoginRequired));
+
+        // Already-correlated errors are not wrapped twice.
+        let err = ctx.correlate(err);
+        assert!(matches!(
+            err,
+            RestError::Correlated { ref source, .. } if matches!(**source, RestError::LoginRequired)
+        ));
+
+        // Errors raised before any network access carry the id too.
+        let err = ctx
+            .do_request_deadline("Misc/Debug:fixedString", "GET", (), Instant::now())
+            .unwrap_err();
+        assert_eq!(err.correlation_id(), Some("trace-1"));
+        assert!(matches!(err.inner(), RestError::DeadlineExceeded));
+    }
+
+    #[test]
+    fn test_version() {
+        assert_eq!(version(), env!("CARGO_PKG_VERSION"));
+        assert_eq!(PROTOCOL_VERSION, format!("klbfw-rs/{}", version()));
+    }
+
     #[test]
     fn test_custom_headers() {
         let ctx = Client::new()
@@ -400,6 +2448,312 @@ mod tests {
         assert_eq!(ctx.headers().len(), 4);
     }
 
+    #[test]
+    fn test_managed_headers_take_precedence() {
+        let capture = Arc::new(Capture::default());
+        let token = Token::new("access".into(), "refresh".into(), "client".into(), 3600);
+        let ctx = Client::new()
+            .with_transport(capture.clone())
+            .with_header("X-Tenant-Id", "t-1")
+            .with_header("sec-rest-http", "true")
+            .with_header("Authorization", "Basic abc")
+            .with_header("Content-Type", "text/plain");
+
+        // Without a token or body, only the framework's own headers win.
+        ctx.do_request("Thing", "GET", ()).unwrap();
+        ctx.clone()
+            .with_token(token)
+            .do_request("Thing", "POST", serde_json::json!({"a": 1}))
+            .unwrap();
+
+        let requests = capture.0.lock().unwrap();
+        let values = |request: &HttpRequest, name: &str| -> Vec<String> {
+            request
+                .headers
+                .iter()
+                .filter(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.clone())
+                .collect()
+        };
+        for request in requests.iter() {
+            assert_eq!(values(request, "X-Tenant-Id"), ["t-1"]);
+            assert_eq!(values(request, "Sec-Rest-Http"), ["false"]);
+        }
+        assert_eq!(values(&requests[0], "Authorization"), ["Basic abc"]);
+        assert_eq!(values(&requests[0], "Content-Type"), ["text/plain"]);
+        assert_eq!(values(&requests[1], "Authorization"), ["Bearer access"]);
+        assert_eq!(values(&requests[1], "Content-Type"), ["application/json"]);
+    }
+
+    #[test]
+    fn test_auth_method() {
+        let ctx = Client::new();
+        assert!(!ctx.is_authenticated());
+        assert_eq!(ctx.auth_method(), None);
+
+        let token = Token::new(
+            "access".to_string(),
+            "refresh".to_string(),
+            "client".to_string(),
+            3600,
+        );
+        let ctx = ctx.with_token(token);
+        assert!(ctx.is_authenticated());
+        assert_eq!(ctx.auth_method(), Some(AuthMethod::Token));
+
+        // An expired token counts only if it can be renewed.
+        let expired = |refresh: &str, client_id: &str| Token {
+            issued_at: 0,
+            ..Token::new(
+                "access".to_string(),
+                refresh.to_string(),
+                client_id.to_string(),
+                3600,
+            )
+        };
+        assert!(Client::new()
+            .with_token(expired("refresh", "client"))
+            .is_authenticated());
+        assert!(!Client::new()
+            .with_token(expired("", "client"))
+            .is_authenticated());
+        let unrenewable = Client::new().with_token(expired("refresh", ""));
+        assert!(!unrenewable.is_authenticated());
+        assert_eq!(unrenewable.auth_method(), Some(AuthMethod::Token));
+
+        let key = ApiKey::new("key".to_string(), &"A".repeat(43)).unwrap();
+        let ctx = ctx.with_api_key(key);
+        assert_eq!(ctx.auth_method(), Some(AuthMethod::ApiKey));
+    }
+
+    #[test]
+    fn test_api_key_and_token() {
+        let capture = Arc::new(Capture::default());
+        let key = ApiKey::new("key".to_string(), &"A".repeat(43)).unwrap();
+        let token = Token::new(
+            "access".to_string(),
+            "refresh".to_string(),
+            "client".to_string(),
+            3600,
+        );
+        let ctx = Client::new()
+            .with_api_key_and_token(key, token)
+            .with_transport(capture.clone());
+        assert_eq!(ctx.auth_method(), Some(AuthMethod::ApiKeyAndToken));
+
+        ctx.do_request("User:get", "GET", ()).unwrap();
+        let requests = capture.0.lock().unwrap();
+        assert!(requests[0].url.contains("_sign="));
+        assert_eq!(requests[0].header("Authorization"), Some("Bearer access"));
+    }
+
+    /// Fails with the given statuses, then succeeds. Counts the requests.
+    struct Flaky {
+        failures: Mutex<Vec<u16>>,
+        count: Mutex<u32>,
+    }
+
+    impl Flaky {
+        fn new(failures: &[u16]) -> Self {
+            Flaky {
+                failures: Mutex::new(failures.iter().rev().copied().collect()),
+                count: Mutex::new(0),
+            }
+        }
+    }
+
+    impl Transport for Flaky {
+        fn send(&self, _request: HttpRequest) -> Result<HttpResponse> {
+            *self.count.lock().unwrap() += 1;
+            let response = match self.failures.lock().unwrap().pop() {
+                Some(status) => HttpResponse::new(status, Vec::new(), b"down".to_vec()),
+                None => HttpResponse::new(200, Vec::new(), br#"{"result":"success"}"#.to_vec()),
+            };
+            Ok(response)
+        }
+    }
+
+    #[test]
+    fn test_profile_sets_retry_policy() {
+        use crate::client::TimeoutProfile;
+        let client = Client::new().with_profile(TimeoutProfile::Interactive);
+        assert_eq!(client.config.timeout(), Duration::from_secs(15));
+        assert_eq!(
+            client.retry_policy(),
+            Some(&TimeoutProfile::Interactive.retry_policy())
+        );
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy::new().with_base_delay(Duration::from_millis(1));
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let client = |transport: &Arc<Flaky>, policy: &RetryPolicy| {
+            let events = events.clone();
+            Client::new()
+                .with_transport(transport.clone())
+                .with_retry(policy.clone())
+                .with_retry_hook(move |event| {
+                    events.lock().unwrap().push((event.attempt, event.outcome));
+                })
+        };
+
+        // Transient failures are retried until the request succeeds.
+        let flaky = Arc::new(Flaky::new(&[504, 429]));
+        client(&flaky, &policy)
+            .do_request("Thing", "GET", ())
+            .unwrap();
+        assert_eq!(*flaky.count.lock().unwrap(), 3);
+        assert_eq!(events.lock().unwrap().len(), 2);
+
+        // Retries run out.
+        events.lock().unwrap().clear();
+        let flaky = Arc::new(Flaky::new(&[502; 5]));
+        let policy = policy.with_max_retries(2);
+        let err = client(&flaky, &policy)
+            .do_request("Thing", "GET", ())
+            .unwrap_err();
+        assert_eq!(err.status_code(), Some(502));
+        assert_eq!(*flaky.count.lock().unwrap(), 3);
+        let events = events.lock().unwrap().clone();
+        assert_eq!(events.last(), Some(&(3, RetryOutcome::GiveUp)));
+
+        // Other statuses, and POST unless allowed, are not retried.
+        let flaky = Arc::new(Flaky::new(&[404]));
+        assert!(client(&flaky, &policy)
+            .do_request("Thing", "GET", ())
+            .is_err());
+        assert_eq!(*flaky.count.lock().unwrap(), 1);
+        let flaky = Arc::new(Flaky::new(&[503]));
+        assert!(client(&flaky, &policy)
+            .do_request("Thing", "POST", ())
+            .is_err());
+        assert_eq!(*flaky.count.lock().unwrap(), 1);
+        let flaky = Arc::new(Flaky::new(&[502]));
+        let policy = policy.with_non_idempotent(true);
+        assert!(client(&flaky, &policy)
+            .do_request("Thing", "POST", ())
+            .is_ok());
+        assert_eq!(*flaky.count.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_request_options() {
+        let capture = Arc::new(Capture::default());
+        let ctx = Client::new()
+            .with_header("Idempotency-Key", "global")
+            .with_transport(capture.clone());
+        let options = RequestOptions::new()
+            .with_idempotency_key("order-1")
+            .with_header("X-Extra", "1");
+        ctx.do_request_with_options("Order", "POST", (), &options)
+            .unwrap();
+        ctx.do_request("Order", "POST", ()).unwrap();
+        let requests = capture.0.lock().unwrap();
+        let values = |request: &HttpRequest, name: &str| -> Vec<String> {
+            request
+                .headers
+                .iter()
+                .filter(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.clone())
+                .collect()
+        };
+        assert_eq!(values(&requests[0], "Idempotency-Key"), ["order-1"]);
+        assert_eq!(values(&requests[0], "X-Extra"), ["1"]);
+        assert_eq!(values(&requests[1], "Idempotency-Key"), ["global"]);
+        assert_eq!(ctx.headers().len(), 1);
+
+        let generated = RequestOptions::new().with_new_idempotency_key();
+        let other = RequestOptions::new().with_new_idempotency_key();
+        assert_eq!(generated.idempotency_key().map(str::len), Some(36));
+        assert_ne!(generated.idempotency_key(), other.idempotency_key());
+
+        // A keyed POST is safe to retry.
+        let flaky = Arc::new(Flaky::new(&[502]));
+        let ctx = Client::new()
+            .with_transport(flaky.clone())
+            .with_retry(RetryPolicy::new().with_base_delay(Duration::from_millis(1)));
+        ctx.do_request_with_options("Order", "POST", (), &generated)
+            .unwrap();
+        assert_eq!(*flaky.count.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::ZERO));
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(Duration::ZERO)
+        );
+        let later = chrono::Utc::now() + chrono::Duration::seconds(90);
+        let left = parse_retry_after(&later.to_rfc2822()).unwrap();
+        assert!(left > Duration::from_secs(80) && left <= Duration::from_secs(90));
+        assert_eq!(parse_retry_after("soon"), None);
+        assert_eq!(parse_retry_after("-5"), None);
+    }
+
+    /// Answers 429 with the given `Retry-After` and body.
+    struct Limited(Option<&'static str>, &'static str);
+
+    impl Transport for Limited {
+        fn send(&self, _request: HttpRequest) -> Result<HttpResponse> {
+            let headers = self
+                .0
+                .map(|value| ("Retry-After".to_string(), value.to_string()))
+                .into_iter()
+                .collect();
+            Ok(HttpResponse::new(429, headers, self.1.as_bytes().to_vec()))
+        }
+    }
+
+    #[test]
+    fn test_rate_limited() {
+        let envelope = r#"{"result":"error","error":"slow down","code":429}"#;
+        let ctx = Client::new().with_transport(Arc::new(Limited(Some("7"), envelope)));
+        let err = ctx.do_request("Thing", "GET", ()).unwrap_err();
+        assert!(matches!(
+            err,
+            RestError::RateLimited { retry_after, ref source }
+                if retry_after == Duration::from_secs(7)
+                    && matches!(**source, RestError::Api { .. })
+        ));
+        assert_eq!(err.status_code(), Some(429));
+
+        let ctx = Client::new().with_transport(Arc::new(Limited(Some("3"), "Too Many")));
+        let err = ctx.do_request("Thing", "GET", ()).unwrap_err();
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(3)));
+        assert!(matches!(err.inner(), RestError::Http { status: 429, .. }));
+
+        // Without the header, the usual error.
+        let ctx = Client::new().with_transport(Arc::new(Limited(None, envelope)));
+        let err = ctx.do_request("Thing", "GET", ()).unwrap_err();
+        assert!(matches!(
+            err,
+            RestError::Api {
+                code: Some(429),
+                ..
+            }
+        ));
+
+        // Retries wait as long as the server asks.
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let ctx = Client::new()
+            .with_transport(Arc::new(Limited(Some("0"), envelope)))
+            .with_retry(RetryPolicy::new().with_base_delay(Duration::from_secs(60)))
+            .with_retry_hook(move |event| recorded.lock().unwrap().push(event.outcome));
+        ctx.do_request("Thing", "GET", ()).unwrap_err();
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            events[0],
+            RetryOutcome::Retry {
+                backoff: Duration::ZERO
+            }
+        );
+    }
+
     #[test]
     #[allow(deprecated)]
     fn test_rest_context_alias() {
@@ -0,0 +1,340 @@
+//! Retrying of requests that fail transiently, and reporting of retries.
+//!
+//! Requests that fail with a transient error (see
+//! [`RestError::is_retryable`]) may be sent again after a backoff delay.
+//! Upload parts are always retried; REST requests are retried when the
+//! client has a [`RetryPolicy`]:
+//!
+//! ```no_run
+//! use klbfw::retry::RetryPolicy;
+//! use klbfw::Client;
+//! use std::time::Duration;
+//!
+//! let ctx = Client::new().with_retry(
+//!     RetryPolicy::new()
+//!         .with_max_retries(5)
+//!         .with_base_delay(Duration::from_millis(500)),
+//! );
+//! ```
+//!
+//! A hook given to [`Client::with_retry_hook`](crate::Client::with_retry_hook)
+//! gets a [`RetryEvent`] for every retry and a final one when retries run
+//! out, to forward to the application's logs:
+//!
+//! ```no_run
+//! use klbfw::Client;
+//!
+//! let ctx = Client::new().with_retry_hook(|event| eprintln!("{}", event));
+//! ```
+//!
+//! With [debug](crate::Client::with_debug) enabled the events are also
+//! printed to stderr.
+
+use crate::error::RestError;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Statuses retried by default: rate limiting and gateway failures
+const DEFAULT_RETRYABLE_STATUSES: [u16; 5] = [429, 500, 502, 503, 504];
+/// Delay before retrying a request failing because the server is in
+/// maintenance, see [`RestError::is_maintenance`].
+pub(crate) const MAINTENANCE_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// When and how failed REST requests are retried
+///
+/// A request is retried when it fails to reach the server (connection
+/// refused or reset, timeout, truncated response) or gets one of the
+/// [retryable statuses](Self::with_retryable_statuses), up to
+/// [`max_retries`](Self::with_max_retries) times. The delay before retry
+/// `n` is `base_delay * 2^(n-1)`, capped to the maximum delay, of which a
+/// random half is kept to spread out clients failing together; a
+/// [rate limited](RestError::RateLimited) request waits for the
+/// server's `Retry-After` instead, and a request failing because the
+/// server is [in maintenance](RestError::is_maintenance) waits 5 seconds.
+/// Retries stop early when the wait would
+/// pass the request deadline.
+///
+/// `POST` and `PATCH` requests may not be safe to send twice and are only
+/// retried with [`with_non_idempotent`](Self::with_non_idempotent), or when
+/// they carry an `Idempotency-Key` header (see
+/// [`RequestOptions::with_idempotency_key`](crate::RequestOptions::with_idempotency_key)).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    retryable_statuses: Vec<u16>,
+    non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(30),
+            retryable_statuses: DEFAULT_RETRYABLE_STATUSES.to_vec(),
+            non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Up to 3 retries from a 200ms delay, on statuses 429, 500, 502, 503
+    /// and 504
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Retry a request at most `max_retries` times
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Wait `base_delay` before the first retry, doubling it for each
+    /// following one
+    pub fn with_base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Never wait more than `max_delay` between attempts (30s by default)
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Retry requests failing with one of `statuses`, from the HTTP status
+    /// or the error code of the response
+    pub fn with_retryable_statuses(mut self, statuses: impl IntoIterator<Item = u16>) -> Self {
+        self.retryable_statuses = statuses.into_iter().collect();
+        self
+    }
+
+    /// Also retry `POST` and `PATCH` requests
+    ///
+    /// Only for endpoints where sending a request twice does no harm: a
+    /// request whose response was lost may have been processed.
+    pub fn with_non_idempotent(mut self, non_idempotent: bool) -> Self {
+        self.non_idempotent = non_idempotent;
+        self
+    }
+
+    /// Maximum number of retries of a request
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Delay before the first retry
+    pub fn base_delay(&self) -> Duration {
+        self.base_delay
+    }
+
+    /// Maximum delay between attempts
+    pub fn max_delay(&self) -> Duration {
+        self.max_delay
+    }
+
+    /// Statuses whose requests are retried
+    pub fn retryable_statuses(&self) -> &[u16] {
+        &self.retryable_statuses
+    }
+
+    /// Whether `POST` and `PATCH` requests are retried
+    pub fn non_idempotent(&self) -> bool {
+        self.non_idempotent
+    }
+
+    /// Whether a `method` request failing with `err` may be sent again;
+    /// `keyed` if it carries an idempotency key.
+    pub(crate) fn should_retry(&self, method: &str, keyed: bool, err: &RestError) -> bool {
+        let idempotent = !matches!(method.to_ascii_uppercase().as_str(), "POST" | "PATCH");
+        if !idempotent && !keyed && !self.non_idempotent {
+            return false;
+        }
+        let status = match err.inner() {
+            RestError::Transport(rsurl::Error::Status { code, .. }) => Some(i32::from(*code)),
+            _ => err.status_code(),
+        };
+        match status {
+            Some(status) => {
+                u16::try_from(status).is_ok_and(|status| self.retryable_statuses.contains(&status))
+            }
+            // No response: retry connection failures only.
+            None => err.is_retryable(),
+        }
+    }
+
+    /// Delay before retry number `retry`, from 1, of a request failing with
+    /// `err`: as long as the server asked, if it did, up to `max_delay`.
+    pub(crate) fn delay(&self, err: &RestError, retry: u32) -> Duration {
+        let delay = match err.retry_after() {
+            Some(retry_after) => retry_after,
+            None if err.is_maintenance() => MAINTENANCE_RETRY_DELAY,
+            None => self.backoff(retry),
+        };
+        delay.min(self.max_delay)
+    }
+
+    /// Delay before retry number `retry`, from 1, with jitter.
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        let factor = 1u32 << retry.saturating_sub(1).min(16);
+        let delay = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        // Keep half the delay, plus a random share of the other half.
+        let random = (uuid::Uuid::new_v4().as_u128() % 1024) as u32;
+        delay / 2 + delay / 2 * random / 1024
+    }
+}
+
+/// Callback receiving the [`RetryEvent`]s of a client
+pub type RetryHookFn = Arc<dyn Fn(&RetryEvent<'_>) + Send + Sync>;
+
+/// What happens after a failed attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryOutcome {
+    /// The request is sent again after waiting `backoff`
+    Retry {
+        /// Delay before the next attempt
+        backoff: Duration,
+    },
+    /// No retries are left; the error is returned
+    GiveUp,
+}
+
+/// A failed attempt of a retried request
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct RetryEvent<'a> {
+    /// Request path; for upload parts, the upload URL without its query
+    /// string followed by the part number
+    pub path: &'a str,
+    /// `X-Request-Id` of the failed attempt, if the server answered
+    pub request_id: Option<&'a str>,
+    /// Number of the failed attempt, from 1
+    pub attempt: u32,
+    /// Error of the failed attempt
+    pub error: &'a RestError,
+    /// Whether the request is retried
+    pub outcome: RetryOutcome,
+}
+
+impl<'a> RetryEvent<'a> {
+    pub(crate) fn new(
+        path: &'a str,
+        attempt: u32,
+        error: &'a RestError,
+        outcome: RetryOutcome,
+    ) -> Self {
+        RetryEvent {
+            path,
+            request_id: error.request_id(),
+            attempt,
+            error,
+            outcome,
+        }
+    }
+}
+
+impl fmt::Display for RetryEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.outcome {
+            RetryOutcome::Retry { backoff } => write!(
+                f,
+                "[retry] {} attempt {} failed, retrying in {:?}: {}",
+                self.path, self.attempt, backoff, self.error
+            )?,
+            RetryOutcome::GiveUp => write!(
+                f,
+                "[retry] {} failed after {} attempts, giving up: {}",
+                self.path, self.attempt, self.error
+            )?,
+        }
+        if let Some(id) = self.request_id {
+            write!(f, " (request id: {})", id)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy() {
+        let policy = RetryPolicy::new();
+        let unavailable = RestError::http(503, "Service Unavailable".to_string(), None);
+        let not_found = RestError::http(404, "Not Found".to_string(), None);
+        let refused = RestError::Transport(rsurl::Error::Io(std::io::Error::from(
+            std::io::ErrorKind::ConnectionRefused,
+        )));
+        assert!(policy.should_retry("GET", false, &unavailable));
+        assert!(policy.should_retry("DELETE", false, &refused));
+        assert!(!policy.should_retry("GET", false, &not_found));
+        assert!(!policy.should_retry("POST", false, &unavailable));
+        assert!(policy.should_retry("POST", true, &unavailable));
+        assert!(!policy.should_retry("GET", false, &RestError::DeadlineExceeded));
+
+        let policy = policy
+            .with_non_idempotent(true)
+            .with_retryable_statuses([404]);
+        assert!(policy.should_retry("post", false, &not_found));
+        assert!(!policy.should_retry("GET", false, &unavailable));
+
+        let policy = RetryPolicy::new()
+            .with_base_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_millis(300));
+        for (retry, full) in [(1, 100), (2, 200), (3, 300), (10, 300)] {
+            let backoff = policy.backoff(retry);
+            let full = Duration::from_millis(full);
+            assert!(backoff >= full / 2 && backoff <= full, "{:?}", backoff);
+        }
+
+        // The server's Retry-After first, then the maintenance delay.
+        let maintenance = RestError::http(503, String::new(), None);
+        let limited = RestError::RateLimited {
+            retry_after: Duration::from_secs(20),
+            source: Box::new(RestError::http(503, String::new(), None)),
+        };
+        let bad_gateway = RestError::http(502, "Bad Gateway".to_string(), None);
+        let default = RetryPolicy::new();
+        assert_eq!(default.delay(&maintenance, 1), MAINTENANCE_RETRY_DELAY);
+        assert_eq!(default.delay(&limited, 1), Duration::from_secs(20));
+        assert!(policy.delay(&bad_gateway, 1) <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_delay_capped() {
+        let policy = RetryPolicy::new().with_max_delay(Duration::from_millis(300));
+        let maintenance = RestError::http(503, String::new(), None);
+        assert_eq!(policy.delay(&maintenance, 1), Duration::from_millis(300));
+        let limited = RestError::RateLimited {
+            retry_after: Duration::from_secs(120),
+            source: Box::new(maintenance),
+        };
+        assert_eq!(policy.delay(&limited, 1), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_display() {
+        let err = RestError::http(502, "Bad Gateway".to_string(), None);
+        let backoff = Duration::from_millis(400);
+        let event = RetryEvent::new("Data/List", 2, &err, RetryOutcome::Retry { backoff });
+        assert_eq!(
+            event.to_string(),
+            "[retry] Data/List attempt 2 failed, retrying in 400ms: HTTP error 502: Bad Gateway"
+        );
+
+        let event = RetryEvent {
+            request_id: Some("req-1"),
+            ..RetryEvent::new("Data/List", 3, &err, RetryOutcome::GiveUp)
+        };
+        assert_eq!(
+            event.to_string(),
+            "[retry] Data/List failed after 3 attempts, giving up: HTTP error 502: \
+             Bad Gateway (request id: req-1)"
+        );
+    }
+}
@@ -1,11 +1,27 @@
-use chrono::{DateTime, Utc};
+use crate::error::{RestError, Result as RestResult};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Utc};
 use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
 
 /// Custom time type that wraps chrono::DateTime and provides custom JSON serialization
 /// matching the format expected by the REST API.
+///
+/// Always in UTC: the `tz` of a time object is ignored when reading it. Use
+/// [`ZonedTime`] to keep the time zone.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Time(pub DateTime<Utc>);
 
+/// A [`Time`] along with the time zone it was given in
+///
+/// Serializes to the same time object as [`Time`], with `tz` naming the
+/// zone and `iso` the local time in it, and keeps both when deserialized.
+/// Build it with [`Time::with_timezone`] or `From<DateTime<FixedOffset>>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZonedTime {
+    time: Time,
+    offset: FixedOffset,
+    tz: String,
+}
+
 /// Internal structure for JSON serialization/deserialization
 #[derive(Debug, Serialize, Deserialize)]
 struct TimeInternal {
@@ -16,7 +32,7 @@ struct TimeInternal {
     /// Timezone (optional)
     #[serde(skip_serializing_if = "Option::is_none")]
     tz: Option<String>,
-    /// ISO 8601 formatted string (optional)
+    /// Local time in `tz`, as `YYYY-MM-DD HH:MM:SS` (optional)
     #[serde(skip_serializing_if = "Option::is_none")]
     iso: Option<String>,
     /// Full timestamp in microseconds as string (optional)
@@ -27,12 +43,32 @@ struct TimeInternal {
     unixms: Option<String>,
 }
 
+/// Forms a [`Time`] is deserialized from.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TimeForm {
+    Full(TimeInternal),
+    Text(String),
+}
+
 impl Time {
     /// Create a new Time from a DateTime
     pub fn new(dt: DateTime<Utc>) -> Self {
         Time(dt)
     }
 
+    /// This time in the time zone at `offset` from UTC
+    ///
+    /// The instant is unchanged; the offset is kept through serialization,
+    /// in the `tz` field.
+    pub fn with_timezone(self, offset: FixedOffset) -> ZonedTime {
+        ZonedTime {
+            time: self,
+            offset,
+            tz: tz_name(offset),
+        }
+    }
+
     /// Create a Time from unix timestamp and microseconds.
     ///
     /// Returns `None` if the resulting timestamp is out of range.
@@ -67,6 +103,46 @@ impl Time {
     pub fn iso(&self) -> String {
         self.0.format("%Y-%m-%d %H:%M:%S").to_string()
     }
+
+    /// Parse a timestamp string.
+    ///
+    /// Accepts the API format produced by [`iso`](Self::iso)
+    /// (`YYYY-MM-DD HH:MM:SS`, read as UTC, optionally with fractional
+    /// seconds), the same with a `T` separator, and RFC 3339
+    /// (`2020-08-12T14:28:11Z`, any offset).
+    pub fn parse(s: &str) -> RestResult<Self> {
+        let s = s.trim();
+        for format in ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"] {
+            if let Ok(dt) = NaiveDateTime::parse_from_str(s, format) {
+                return Ok(Time(dt.and_utc()));
+            }
+        }
+        DateTime::parse_from_rfc3339(s)
+            .map(|dt| Time(dt.with_timezone(&Utc)))
+            .map_err(|_| {
+                RestError::Other(format!(
+                    "invalid time {:?}: expected \"YYYY-MM-DD HH:MM:SS\" or RFC 3339",
+                    s
+                ))
+            })
+    }
+
+    /// Parse an ISO 8601 timestamp such as `2020-08-12 14:28:11` or
+    /// `2020-08-12T14:28:11Z`
+    ///
+    /// Same as [`parse`](Self::parse); timestamps without an offset are UTC.
+    /// This is also how `Time` deserializes from a JSON string.
+    pub fn parse_iso(s: &str) -> RestResult<Self> {
+        Time::parse(s)
+    }
+}
+
+impl TryFrom<&str> for Time {
+    type Error = RestError;
+
+    fn try_from(s: &str) -> RestResult<Self> {
+        Time::parse(s)
+    }
 }
 
 impl From<DateTime<Utc>> for Time {
@@ -75,12 +151,60 @@ impl From<DateTime<Utc>> for Time {
     }
 }
 
+impl From<DateTime<FixedOffset>> for Time {
+    fn from(dt: DateTime<FixedOffset>) -> Self {
+        Time(dt.with_timezone(&Utc))
+    }
+}
+
 impl From<Time> for DateTime<Utc> {
     fn from(t: Time) -> Self {
         t.0
     }
 }
 
+impl ZonedTime {
+    /// The instant of this time
+    pub fn time(&self) -> Time {
+        self.time
+    }
+
+    /// Offset from UTC of the time zone of this time
+    ///
+    /// From the `tz` field of the API's time object: `UTC` or an offset
+    /// such as `+09:00`. For a named zone such as `Asia/Tokyo` this is the
+    /// offset at this instant, worked out from the local time sent along in
+    /// `iso` as no time zone database is bundled: the name is kept in
+    /// [`tz`](Self::tz), but other instants in the zone may have another
+    /// offset. A named zone without `iso` is read as UTC.
+    pub fn timezone(&self) -> FixedOffset {
+        self.offset
+    }
+
+    /// Name of the time zone, as sent in `tz`: `UTC`, an offset such as
+    /// `+09:00` or a zone name such as `Asia/Tokyo`
+    pub fn tz(&self) -> &str {
+        &self.tz
+    }
+
+    /// This time as a date and time in its time zone
+    pub fn local(&self) -> DateTime<FixedOffset> {
+        self.time.0.with_timezone(&self.offset)
+    }
+}
+
+impl From<DateTime<FixedOffset>> for ZonedTime {
+    fn from(dt: DateTime<FixedOffset>) -> Self {
+        Time::from(dt).with_timezone(*dt.offset())
+    }
+}
+
+impl From<ZonedTime> for Time {
+    fn from(zoned: ZonedTime) -> Self {
+        zoned.time
+    }
+}
+
 impl Serialize for Time {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -103,9 +227,138 @@ impl<'de> Deserialize<'de> for Time {
     where
         D: Deserializer<'de>,
     {
-        let internal = TimeInternal::deserialize(deserializer)?;
-        Time::from_unix(internal.unix, internal.us)
-            .ok_or_else(|| D::Error::custom("timestamp out of range"))
+        match TimeForm::deserialize(deserializer)? {
+            TimeForm::Full(internal) => Time::from_unix(internal.unix, internal.us)
+                .ok_or_else(|| D::Error::custom("timestamp out of range")),
+            TimeForm::Text(text) => Time::parse_iso(&text).map_err(D::Error::custom),
+        }
+    }
+}
+
+impl Serialize for ZonedTime {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // `iso` is the local time in `tz`, which is how named zones are
+        // resolved when reading it back.
+        let internal = TimeInternal {
+            unix: self.time.unix(),
+            us: self.time.usec(),
+            tz: Some(self.tz.clone()),
+            iso: Some(self.local().format("%Y-%m-%d %H:%M:%S").to_string()),
+            full: Some(self.time.unix_micro().to_string()),
+            unixms: Some(self.time.unix_milli().to_string()),
+        };
+        internal.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ZonedTime {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match TimeForm::deserialize(deserializer)? {
+            TimeForm::Full(internal) => {
+                let time = Time::from_unix(internal.unix, internal.us)
+                    .ok_or_else(|| D::Error::custom("timestamp out of range"))?;
+                let zone = internal.tz.and_then(|tz| {
+                    let offset = parse_tz(&tz, internal.iso.as_deref(), time.0)?;
+                    Some(ZonedTime { time, offset, tz })
+                });
+                Ok(zone.unwrap_or_else(|| time.with_timezone(utc())))
+            }
+            TimeForm::Text(text) => match DateTime::parse_from_rfc3339(text.trim()) {
+                Ok(dt) => Ok(ZonedTime::from(dt)),
+                Err(_) => Time::parse_iso(&text)
+                    .map(|time| time.with_timezone(utc()))
+                    .map_err(D::Error::custom),
+            },
+        }
+    }
+}
+
+/// The UTC offset.
+fn utc() -> FixedOffset {
+    FixedOffset::east_opt(0).unwrap()
+}
+
+/// `tz` field for `offset`: `UTC`, or the offset such as `+09:00`.
+fn tz_name(offset: FixedOffset) -> String {
+    if offset.local_minus_utc() == 0 {
+        "UTC".to_string()
+    } else {
+        offset.to_string()
+    }
+}
+
+/// Offset of the `tz` field of a time object at `instant`. Named zones are
+/// resolved from `iso`, the local time of the instant in that zone.
+fn parse_tz(tz: &str, iso: Option<&str>, instant: DateTime<Utc>) -> Option<FixedOffset> {
+    if tz.eq_ignore_ascii_case("UTC") || tz == "Z" {
+        return Some(utc());
+    }
+    if let Ok(offset) = tz.parse::<FixedOffset>() {
+        return Some(offset);
+    }
+    let local = NaiveDateTime::parse_from_str(iso?.trim(), "%Y-%m-%d %H:%M:%S%.f").ok()?;
+    // The iso form has whole seconds; round the offset to minutes.
+    let seconds = (local - instant.naive_utc()).num_seconds();
+    FixedOffset::east_opt(((seconds as f64 / 60.0).round() as i32) * 60)
+}
+
+/// Serde helper serializing a [`Time`] as a bare unix timestamp in seconds
+///
+/// For request parameters of endpoints that expect a number rather than the
+/// full time object:
+///
+/// ```
+/// use klbfw::Time;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Params {
+///     #[serde(with = "klbfw::time::compact")]
+///     since: Time,
+/// }
+///
+/// let params = Params { since: Time::from_unix(1597242491, 0).unwrap() };
+/// assert_eq!(serde_json::to_string(&params).unwrap(), r#"{"since":1597242491}"#);
+/// ```
+///
+/// Sub-second precision is dropped. Deserialization accepts the integer as
+/// well as the full object form.
+pub mod compact {
+    use super::{Time, TimeInternal};
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    /// Either form of a serialized time.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum AnyTime {
+        Unix(i64),
+        Full(TimeInternal),
+    }
+
+    /// Serialize `time` as its unix timestamp in seconds.
+    pub fn serialize<S>(time: &Time, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i64(time.unix())
+    }
+
+    /// Deserialize a unix timestamp in seconds, or a full time object.
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Time, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (unix, us) = match AnyTime::deserialize(deserializer)? {
+            AnyTime::Unix(unix) => (unix, 0),
+            AnyTime::Full(internal) => (internal.unix, internal.us),
+        };
+        Time::from_unix(unix, us).ok_or_else(|| D::Error::custom("timestamp out of range"))
     }
 }
 
@@ -132,6 +385,104 @@ mod tests {
 
         assert_eq!(time.unix(), 1597242491);
         assert_eq!(time.usec(), 747497);
+
+        // Timestamps sent as strings.
+        let time: Time = serde_json::from_str(r#""2020-08-12 14:28:11""#).unwrap();
+        assert_eq!(time.unix(), 1597242491);
+        let times: Vec<Time> =
+            serde_json::from_str(r#"[{"unix": 1597242491, "us": 0}, "2020-08-12T14:28:11Z"]"#)
+                .unwrap();
+        assert_eq!(times[0], times[1]);
+        assert!(serde_json::from_str::<Time>(r#""soon""#).is_err());
+    }
+
+    #[test]
+    fn test_time_round_trip() {
+        let time = Time::from_unix(1597242491, 747497).unwrap();
+        let json = serde_json::to_string(&time).unwrap();
+        assert_eq!(serde_json::from_str::<Time>(&json).unwrap(), time);
+    }
+
+    #[test]
+    fn test_time_compact() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Params {
+            #[serde(with = "compact")]
+            since: Time,
+        }
+
+        let params = Params {
+            since: Time::from_unix(1597242491, 0).unwrap(),
+        };
+        let json = serde_json::to_string(&params).unwrap();
+        assert_eq!(json, r#"{"since":1597242491}"#);
+        assert_eq!(serde_json::from_str::<Params>(&json).unwrap(), params);
+
+        // The full object form is accepted too, keeping its microseconds.
+        let params: Params =
+            serde_json::from_str(r#"{"since": {"unix": 1597242491, "us": 747497}}"#).unwrap();
+        assert_eq!(params.since.usec(), 747497);
+    }
+
+    #[test]
+    fn test_time_parse() {
+        let time = Time::parse("2020-08-12 14:28:11").unwrap();
+        assert_eq!(time.unix(), 1597242491);
+        assert_eq!(time.iso(), "2020-08-12 14:28:11");
+
+        let time = Time::try_from("2020-08-12 14:28:11.747497").unwrap();
+        assert_eq!(time.usec(), 747497);
+
+        let time = Time::parse("2020-08-12T14:28:11Z").unwrap();
+        assert_eq!(time.unix(), 1597242491);
+        let time = Time::parse_iso("2020-08-12T14:28:11").unwrap();
+        assert_eq!(time.unix(), 1597242491);
+        let time = Time::parse("2020-08-12T23:28:11+09:00").unwrap();
+        assert_eq!(time.unix(), 1597242491);
+
+        let err = Time::parse("yesterday").unwrap_err();
+        assert!(err.to_string().contains("yesterday"), "{}", err);
+    }
+
+    #[test]
+    fn test_time_timezone() {
+        let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+        let time = Time::from_unix(1597242491, 0).unwrap();
+        assert_eq!(serde_json::to_value(time).unwrap()["tz"], "UTC");
+
+        let local = time.with_timezone(tokyo);
+        assert_eq!(local.time(), time);
+        assert_eq!(local.tz(), "+09:00");
+        assert_eq!(local.local().to_rfc3339(), "2020-08-12T23:28:11+09:00");
+        let value = serde_json::to_value(&local).unwrap();
+        assert_eq!(value["tz"], "+09:00");
+        assert_eq!(value["iso"], "2020-08-12 23:28:11");
+        assert_eq!(value["unix"], 1597242491);
+        let back: ZonedTime = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(back, local);
+        // A plain Time reads the same object as UTC.
+        assert_eq!(serde_json::from_value::<Time>(value).unwrap(), time);
+
+        // A named zone, resolved from the local time sent along, keeps its
+        // name.
+        let json =
+            r#"{"unix": 1597242491, "us": 0, "tz": "Asia/Tokyo", "iso": "2020-08-12 23:28:11"}"#;
+        let zoned: ZonedTime = serde_json::from_str(json).unwrap();
+        assert_eq!(zoned.timezone(), tokyo);
+        assert_eq!(zoned.tz(), "Asia/Tokyo");
+        let value = serde_json::to_value(&zoned).unwrap();
+        assert_eq!(value["tz"], "Asia/Tokyo");
+        assert_eq!(serde_json::from_value::<ZonedTime>(value).unwrap(), zoned);
+
+        let zoned: ZonedTime =
+            serde_json::from_str(r#"{"unix": 1597242491, "us": 0, "tz": "Asia/Tokyo"}"#).unwrap();
+        assert_eq!((zoned.timezone(), zoned.tz()), (utc(), "UTC"));
+
+        let zoned: ZonedTime = serde_json::from_str(r#""2020-08-12T23:28:11+09:00""#).unwrap();
+        assert_eq!((zoned.time(), zoned.timezone()), (time, tokyo));
+        let dt = DateTime::parse_from_rfc3339("2020-08-12T23:28:11+09:00").unwrap();
+        assert_eq!(Time::from(dt), time);
+        assert_eq!(ZonedTime::from(dt), local);
     }
 
     #[test]
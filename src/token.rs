@@ -1,4 +1,6 @@
+use crate::error::{RestError, Result};
 use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Token represents an OAuth2 token with refresh capabilities.
 /// It contains both access and refresh tokens for authentication.
@@ -23,6 +25,14 @@ pub struct Token {
     /// Token expiration time in seconds
     #[serde(rename = "expires_in")]
     pub expires_in: i32,
+
+    /// Unix time the token was issued, in seconds
+    ///
+    /// Set by [`Token::new`]. Server responses do not include it, so a token
+    /// deserialized without it is taken as issued at that moment; a saved
+    /// token keeps its original value.
+    #[serde(default = "unix_now")]
+    pub issued_at: i64,
 }
 
 impl Token {
@@ -39,9 +49,37 @@ impl Token {
             token_type: "Bearer".to_string(),
             client_id,
             expires_in,
+            issued_at: unix_now(),
         }
     }
 
+    /// Build an access-only token from an `Authorization` header value
+    ///
+    /// Accepts `Bearer <token>` (the scheme is case-insensitive), as received
+    /// by a gateway that forwards its caller's credentials. The token has no
+    /// refresh token or client ID, so it is never renewed: once it expires,
+    /// requests fail with the server's error. `expires_in` is 0 (unknown).
+    pub fn from_bearer(header_value: &str) -> Result<Self> {
+        let invalid = || {
+            RestError::Other(format!(
+                "invalid Authorization header, expected `Bearer <token>`: {:?}",
+                header_value
+            ))
+        };
+
+        let (scheme, token) = header_value.trim().split_once(' ').ok_or_else(invalid)?;
+        let token = token.trim_start();
+        if !scheme.eq_ignore_ascii_case("Bearer") || !is_b64token(token) {
+            return Err(invalid());
+        }
+        Ok(Token::new(
+            token.to_string(),
+            String::new(),
+            String::new(),
+            0,
+        ))
+    }
+
     /// Check if we have a refresh token available
     pub fn has_refresh_token(&self) -> bool {
         !self.refresh_token.is_empty()
@@ -51,6 +89,36 @@ impl Token {
     pub fn has_client_id(&self) -> bool {
         !self.client_id.is_empty()
     }
+
+    /// Unix time the token expires at, in seconds, or `None` when its
+    /// lifetime is unknown (`expires_in` of 0 or less)
+    pub fn expires_at(&self) -> Option<i64> {
+        (self.expires_in > 0).then(|| self.issued_at + i64::from(self.expires_in))
+    }
+
+    /// Whether the token's lifetime has run out
+    ///
+    /// Always `false` when the lifetime is unknown.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at().is_some_and(|at| unix_now() >= at)
+    }
+}
+
+/// Current unix time in seconds.
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Whether `s` matches RFC 6750's `b64token`: `[A-Za-z0-9-._~+/]+=*`.
+fn is_b64token(s: &str) -> bool {
+    let body = s.trim_end_matches('=');
+    !body.is_empty()
+        && body
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-._~+/".contains(&b))
 }
 
 #[cfg(test)]
@@ -86,5 +154,59 @@ mod tests {
         let json = serde_json::to_string(&token).unwrap();
         assert!(json.contains("access_token"));
         assert!(json.contains("refresh_token"));
+
+        // A saved token keeps its issue time; a server payload gets now.
+        let mut old = token.clone();
+        old.issued_at = 1000;
+        let saved: Token = serde_json::from_str(&serde_json::to_string(&old).unwrap()).unwrap();
+        assert_eq!(saved.issued_at, 1000);
+        let fresh: Token = serde_json::from_str(
+            r#"{"access_token":"a","refresh_token":"r","token_type":"Bearer","expires_in":60}"#,
+        )
+        .unwrap();
+        assert!(fresh.issued_at >= token.issued_at);
+    }
+
+    #[test]
+    fn test_token_expiry() {
+        let mut token = Token::new("a".into(), "r".into(), "c".into(), 3600);
+        assert_eq!(token.expires_at(), Some(token.issued_at + 3600));
+        assert!(!token.is_expired());
+
+        token.issued_at -= 3600;
+        assert!(token.is_expired());
+
+        // Unknown lifetimes never expire client-side.
+        token.expires_in = 0;
+        assert_eq!(token.expires_at(), None);
+        assert!(!token.is_expired());
+    }
+
+    #[test]
+    fn test_token_from_bearer() {
+        let token = Token::from_bearer("Bearer abc.DEF-123_~+/==").unwrap();
+        assert_eq!(token.access_token, "abc.DEF-123_~+/==");
+        assert_eq!(token.token_type, "Bearer");
+        assert!(!token.has_refresh_token());
+        assert!(!token.has_client_id());
+
+        let token = Token::from_bearer("  bearer   xyz ").unwrap();
+        assert_eq!(token.access_token, "xyz");
+
+        for bad in [
+            "",
+            "Bearer",
+            "Bearer ",
+            "Basic abc",
+            "abc",
+            "Bearer a b",
+            "Bearer ==",
+        ] {
+            assert!(
+                matches!(Token::from_bearer(bad), Err(RestError::Other(_))),
+                "{:?}",
+                bad
+            );
+        }
     }
 }
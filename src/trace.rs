@@ -0,0 +1,231 @@
+//! Capture of the exact bytes exchanged by a single request.
+//!
+//! [`Client::do_request_traced`] runs a request like
+//! [`Client::do_request`] and also returns a [`RequestTrace`] of what went
+//! over the wire, for attaching to bug reports.
+//! [`Client::curl_command`] renders a request as a runnable curl command
+//! instead of sending it.
+//!
+//! [`Client::do_request_traced`]: crate::Client::do_request_traced
+//! [`Client::do_request`]: crate::Client::do_request
+//! [`Client::curl_command`]: crate::Client::curl_command
+
+use crate::error::Result;
+use crate::transport::{HttpRequest, HttpResponse, Transport};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Replacement for redacted values.
+pub(crate) const REDACTED: &str = "[redacted]";
+/// Headers carrying credentials.
+pub(crate) const SECRET_HEADERS: [&str; 2] = ["Authorization", "X-Amz-Security-Token"];
+/// Query parameters carrying credentials, besides an API key's renamed
+/// signature.
+pub(crate) const SECRET_PARAMS: [&str; 3] = ["_sign", "access_token", "refresh_token"];
+
+/// The HTTP exchange of a request, as sent and received
+///
+/// When a request is retried (e.g. after renewing an expired token), this is
+/// the last attempt. Response fields are empty when no response was
+/// received. Call [`redacted`](Self::redacted) before sharing it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RequestTrace {
+    /// HTTP method
+    pub method: String,
+    /// Final URL, including the query string
+    pub url: String,
+    /// Request headers, in the order they were sent
+    pub request_headers: Vec<(String, String)>,
+    /// Request body
+    pub request_body: Vec<u8>,
+    /// Response status, if a response was received
+    pub status: Option<u16>,
+    /// Response headers
+    pub response_headers: Vec<(String, String)>,
+    /// Raw response body
+    pub response_body: Vec<u8>,
+    /// Name of the signature parameter of the client's API key, if renamed
+    sign_param: Option<String>,
+}
+
+impl RequestTrace {
+    /// A copy with credentials masked: the `Authorization` and
+    /// `X-Amz-Security-Token` headers and the `_sign`, `access_token` and
+    /// `refresh_token` query parameters, the signature parameter also under
+    /// its [custom name](crate::ApiKey::with_param_names)
+    pub fn redacted(&self) -> RequestTrace {
+        let mut trace = self.clone();
+        redact_headers(&mut trace.request_headers);
+        trace.url = redact_url(&trace.url, trace.sign_param.as_deref());
+        trace
+    }
+
+    /// Mask `name` too when redacting, for API keys with a renamed
+    /// signature parameter.
+    pub(crate) fn with_sign_param(mut self, name: Option<&str>) -> Self {
+        self.sign_param = name.map(String::from);
+        self
+    }
+}
+
+/// Mask the values of credential headers.
+fn redact_headers(headers: &mut [(String, String)]) {
+    for (name, value) in headers {
+        if SECRET_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name)) {
+            *value = REDACTED.to_string();
+        }
+    }
+}
+
+/// `url` with credential query parameters masked, `sign_param` included.
+fn redact_url(url: &str, sign_param: Option<&str>) -> String {
+    let Some((base, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let query = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(form_urlencoded::parse(query.as_bytes()).map(|(k, v)| {
+            let v = if SECRET_PARAMS.contains(&k.as_ref()) || sign_param == Some(k.as_ref()) {
+                REDACTED.into()
+            } else {
+                v
+            };
+            (k, v)
+        }))
+        .finish();
+    format!("{}?{}", base, query)
+}
+
+/// Render `request` as a curl command line, with credentials masked if
+/// `redact` is set, including the signature parameter `sign_param`.
+pub(crate) fn curl_command(
+    request: &HttpRequest,
+    redact: bool,
+    sign_param: Option<&str>,
+) -> String {
+    let mut headers = request.headers.clone();
+    let url = if redact {
+        redact_headers(&mut headers);
+        redact_url(&request.url, sign_param)
+    } else {
+        request.url.clone()
+    };
+
+    let mut command = format!("curl -X {} {}", request.method, shell_quote(&url));
+    for (name, value) in &headers {
+        command.push_str(" \\\n  -H ");
+        command.push_str(&shell_quote(&format!("{}: {}", name, value)));
+    }
+    if !request.body.is_empty() {
+        command.push_str(" \\\n  --data-binary ");
+        command.push_str(&shell_quote(&String::from_utf8_lossy(&request.body)));
+    }
+    command
+}
+
+/// Quote `s` as a single POSIX shell word.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Transport that remembers the last exchange it forwarded.
+pub(crate) struct TracingTransport {
+    inner: Arc<dyn Transport>,
+    last: Mutex<RequestTrace>,
+}
+
+impl TracingTransport {
+    pub(crate) fn new(inner: Arc<dyn Transport>) -> Self {
+        TracingTransport {
+            inner,
+            last: Mutex::new(RequestTrace::default()),
+        }
+    }
+
+    /// The last exchange, leaving an empty trace in its place.
+    pub(crate) fn take(&self) -> RequestTrace {
+        std::mem::take(&mut *self.last.lock().unwrap())
+    }
+}
+
+impl Transport for TracingTransport {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let mut trace = RequestTrace {
+            method: request.method.clone(),
+            url: request.url.clone(),
+            request_headers: request.headers.clone(),
+            request_body: request.body.clone(),
+            ..RequestTrace::default()
+        };
+
+        let result = self.inner.send(request);
+        if let Ok(ref response) = result {
+            trace.status = Some(response.status);
+            trace.response_headers = response.headers.clone();
+            trace.response_body = response.body.clone();
+        }
+        *self.last.lock().unwrap() = trace;
+        result
+    }
+
+    fn check_tls(&self, url: &str, timeout: Duration) -> Option<bool> {
+        self.inner.check_tls(url, timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacted() {
+        let trace = RequestTrace {
+            method: "GET".to_string(),
+            url: "https://h/_special/rest/User?_key=k&_sign=abc&access_token=t&_=%7B%7D"
+                .to_string(),
+            request_headers: vec![
+                ("authorization".to_string(), "Bearer secret".to_string()),
+                ("Sec-Rest-Http".to_string(), "false".to_string()),
+            ],
+            ..RequestTrace::default()
+        };
+
+        let redacted = trace.redacted();
+        assert_eq!(
+            redacted.url,
+            "https://h/_special/rest/User?_key=k&_sign=%5Bredacted%5D&access_token=%5Bredacted%5D&_=%7B%7D"
+        );
+
+        let renamed = RequestTrace {
+            url: "https://h/_special/rest/User?_key=k&sig=abc".to_string(),
+            ..RequestTrace::default()
+        }
+        .with_sign_param(Some("sig"));
+        assert_eq!(
+            renamed.redacted().url,
+            "https://h/_special/rest/User?_key=k&sig=%5Bredacted%5D"
+        );
+        assert_eq!(redacted.request_headers[0].1, REDACTED);
+        assert_eq!(redacted.request_headers[1].1, "false");
+    }
+
+    #[test]
+    fn test_curl_command() {
+        let mut request = HttpRequest::new("POST", "https://h/_special/rest/User?_sign=abc");
+        request
+            .headers
+            .push(("Authorization".to_string(), "Bearer secret".to_string()));
+        request.body = br#"{"name":"O'Brien"}"#.to_vec();
+
+        assert_eq!(
+            curl_command(&request, false, None),
+            "curl -X POST 'https://h/_special/rest/User?_sign=abc' \\\n  \
+             -H 'Authorization: Bearer secret' \\\n  \
+             --data-binary '{\"name\":\"O'\\''Brien\"}'"
+        );
+
+        let redacted = curl_command(&request, true, None);
+        assert!(!redacted.contains("secret"));
+        assert!(!redacted.contains("abc"));
+    }
+}
@@ -0,0 +1,313 @@
+//! Pluggable HTTP transport for REST requests.
+//!
+//! [`Client`](crate::Client) builds each REST call as an [`HttpRequest`] and
+//! hands it to a [`Transport`]. The default, [`RsurlTransport`], sends it over
+//! the network with rsurl; tests and tooling can substitute their own with
+//! [`Client::with_transport`](crate::Client::with_transport), e.g. to record
+//! and replay interactions (see the `record-replay` feature).
+
+use crate::apikey::ParamNames;
+use crate::error::Result;
+use std::io::{Cursor, Read};
+use std::time::Duration;
+
+/// A fully built HTTP request, ready to send
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct HttpRequest {
+    /// HTTP method
+    pub method: String,
+    /// Absolute URL, including the query string
+    pub url: String,
+    /// Request headers, in the order they are sent
+    pub headers: Vec<(String, String)>,
+    /// Request body (empty for none)
+    pub body: Vec<u8>,
+    /// Overall time limit for the exchange
+    pub timeout: Duration,
+    /// Time limit for establishing the connection
+    pub connect_timeout: Duration,
+    /// Names of the API key signing parameters in the query string, as set
+    /// by the [`Client`](crate::Client) that built the request
+    pub param_names: ParamNames,
+}
+
+impl HttpRequest {
+    /// Create a request with no headers or body and default timeouts
+    pub fn new(method: impl Into<String>, url: impl Into<String>) -> Self {
+        HttpRequest {
+            method: method.into(),
+            url: url.into(),
+            headers: Vec::new(),
+            body: Vec::new(),
+            timeout: Duration::from_secs(300),
+            connect_timeout: Duration::from_secs(10),
+            param_names: ParamNames::default(),
+        }
+    }
+
+    /// Get the first header named `name` (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+/// An HTTP response as returned by a [`Transport`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct HttpResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// Response headers
+    pub headers: Vec<(String, String)>,
+    /// Response body
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Create a response
+    pub fn new(status: u16, headers: Vec<(String, String)>, body: Vec<u8>) -> Self {
+        HttpResponse {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    /// Get the first header named `name` (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+/// An HTTP response whose body is read as it arrives
+///
+/// Returned by [`Transport::send_streaming`].
+#[non_exhaustive]
+pub struct StreamingResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// Response headers
+    pub headers: Vec<(String, String)>,
+    /// Response body
+    pub body: Box<dyn Read>,
+}
+
+impl StreamingResponse {
+    /// Create a response
+    pub fn new(status: u16, headers: Vec<(String, String)>, body: Box<dyn Read>) -> Self {
+        StreamingResponse {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    /// Get the first header named `name` (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+impl From<HttpResponse> for StreamingResponse {
+    fn from(response: HttpResponse) -> Self {
+        StreamingResponse::new(
+            response.status,
+            response.headers,
+            Box::new(Cursor::new(response.body)),
+        )
+    }
+}
+
+impl std::fmt::Debug for StreamingResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamingResponse")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+/// Sends [`HttpRequest`]s on behalf of a [`Client`](crate::Client)
+///
+/// Implementations must be shareable across threads, as clones of a client
+/// share one transport.
+pub trait Transport: Send + Sync {
+    /// Send `request` and return the response, whatever its status
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse>;
+
+    /// Send `request` and return the response with its body unread
+    ///
+    /// Used for responses too large to hold in memory. The default buffers
+    /// the body with [`send`](Transport::send).
+    fn send_streaming(&self, request: HttpRequest) -> Result<StreamingResponse> {
+        self.send(request).map(StreamingResponse::from)
+    }
+
+    /// Whether the server at `url` presents a valid TLS certificate
+    ///
+    /// `Some(true)` when a handshake with certificate verification enabled
+    /// succeeds within `timeout`, `Some(false)` when only one without
+    /// verification does, and `None` when this cannot be told: plain HTTP,
+    /// an unreachable host, or a transport not using the network (the
+    /// default).
+    fn check_tls(&self, url: &str, timeout: Duration) -> Option<bool> {
+        let _ = (url, timeout);
+        None
+    }
+}
+
+/// The default transport, sending requests over the network with rsurl
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RsurlTransport;
+
+impl RsurlTransport {
+    fn build(request: HttpRequest) -> Result<rsurl::Request> {
+        let mut builder = rsurl::Request::new(&request.method, &request.url)?
+            .max_time(request.timeout)
+            .connect_timeout(request.connect_timeout);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if !request.body.is_empty() {
+            builder = builder.body(request.body);
+        }
+        Ok(builder)
+    }
+}
+
+impl Transport for RsurlTransport {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let response = Self::build(request)?.send()?;
+        Ok(HttpResponse {
+            status: response.status,
+            headers: response.headers,
+            body: response.body,
+        })
+    }
+
+    fn check_tls(&self, url: &str, timeout: Duration) -> Option<bool> {
+        if !url.starts_with("https://") {
+            return None;
+        }
+        let handshake = |verify: bool| {
+            rsurl::Request::new("HEAD", url).is_ok_and(|request| {
+                request
+                    .verify_tls(verify)
+                    .max_time(timeout)
+                    .connect_timeout(timeout)
+                    .send()
+                    .is_ok()
+            })
+        };
+        if handshake(true) {
+            Some(true)
+        } else if handshake(false) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    fn send_streaming(&self, request: HttpRequest) -> Result<StreamingResponse> {
+        let reader = Self::build(request)?.send_reader()?;
+        Ok(StreamingResponse::new(
+            reader.status(),
+            reader.head().headers.clone(),
+            Box::new(reader),
+        ))
+    }
+}
+
+/// Transports answering with canned responses, shared by the tests.
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::{HttpRequest, HttpResponse, Transport};
+    use crate::error::Result;
+    use std::sync::Mutex;
+
+    /// Records every request and answers with the same response, an empty
+    /// success by default.
+    pub(crate) struct Capture(pub(crate) Mutex<Vec<HttpRequest>>, pub(crate) HttpResponse);
+
+    impl Capture {
+        /// Answer with a 200 response of `body`.
+        pub(crate) fn answering(body: &str) -> Self {
+            let response = HttpResponse::new(200, Vec::new(), body.as_bytes().to_vec());
+            Capture(Mutex::new(Vec::new()), response)
+        }
+    }
+
+    impl Default for Capture {
+        fn default() -> Self {
+            Capture::answering(r#"{"result":"success"}"#)
+        }
+    }
+
+    impl Transport for Capture {
+        fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+            self.0.lock().unwrap().push(request);
+            Ok(self.1.clone())
+        }
+    }
+
+    /// Answers every request with a fixed status and body.
+    pub(crate) struct Fixed<B = &'static str>(pub(crate) u16, pub(crate) B);
+
+    impl<B: AsRef<str> + Send + Sync> Transport for Fixed<B> {
+        fn send(&self, _request: HttpRequest) -> Result<HttpResponse> {
+            let body = self.1.as_ref().as_bytes().to_vec();
+            Ok(HttpResponse::new(self.0, Vec::new(), body))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_lookup() {
+        let mut request = HttpRequest::new("GET", "https://example.com/");
+        request
+            .headers
+            .push(("Content-Type".to_string(), "text/plain".to_string()));
+        assert_eq!(request.header("content-type"), Some("text/plain"));
+        assert_eq!(request.header("Accept"), None);
+
+        let response = HttpResponse::new(
+            200,
+            vec![("x-request-id".to_string(), "abc".to_string())],
+            Vec::new(),
+        );
+        assert_eq!(response.header("X-Request-Id"), Some("abc"));
+    }
+
+    #[test]
+    fn test_check_tls_without_handshake() {
+        let timeout = Duration::from_secs(2);
+        assert_eq!(
+            RsurlTransport.check_tls("http://127.0.0.1:1/", timeout),
+            None
+        );
+
+        // A host closing every connection completes no handshake either way.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                drop(stream);
+            }
+        });
+        let url = format!("https://127.0.0.1:{}/", port);
+        assert_eq!(RsurlTransport.check_tls(&url, timeout), None);
+        server.join().unwrap();
+    }
+}
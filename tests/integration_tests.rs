@@ -1,16 +1,43 @@
-use klbfw::{Client, RestError};
+use klbfw::{debug, Client, RestError};
 use serde::Deserialize;
-use std::collections::HashMap;
+
+/// Client for the tests.
+///
+/// With the `record-replay` feature the tests replay
+/// `tests/fixtures/integration.json` offline; `KLBFW_REPLAY=<file>` replays
+/// another recording and `KLBFW_RECORD=<file>` records the live exchanges to
+/// a file instead. Without the feature they hit the live API.
+#[cfg(feature = "record-replay")]
+fn client() -> Client {
+    use klbfw::replay::{RecordingTransport, ReplayTransport};
+    use std::sync::Arc;
+
+    if let Ok(path) = std::env::var("KLBFW_RECORD") {
+        return Client::new().with_transport(Arc::new(RecordingTransport::new(path)));
+    }
+    let path = std::env::var("KLBFW_REPLAY").unwrap_or_else(|_| {
+        concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/integration.json"
+        )
+        .to_string()
+    });
+    let replay = ReplayTransport::from_file(path).expect("failed to load recording");
+    Client::new().with_transport(Arc::new(replay))
+}
+
+#[cfg(not(feature = "record-replay"))]
+fn client() -> Client {
+    Client::new()
+}
 
 #[test]
-#[ignore] // Run with: cargo test --test integration_tests -- --ignored
+// Live without record-replay: cargo test --test integration_tests -- --ignored
+#[cfg_attr(not(feature = "record-replay"), ignore)]
 fn test_fixed_array() {
-    let ctx = Client::new();
+    let ctx = client();
 
-    // Using apply to unmarshal into a map
-    let result: HashMap<String, serde_json::Value> = ctx
-        .apply("Misc/Debug:fixedArray", "GET", serde_json::json!({}))
-        .expect("failed to call fixedArray");
+    let result = debug::fixed_array(&ctx).expect("failed to call fixedArray");
 
     // Verify we got a non-empty result
     assert!(
@@ -22,18 +49,11 @@ fn test_fixed_array() {
 }
 
 #[test]
-#[ignore]
+#[cfg_attr(not(feature = "record-replay"), ignore)]
 fn test_fixed_string() {
-    let ctx = Client::new();
-
-    let response = ctx
-        .do_request("Misc/Debug:fixedString", "GET", serde_json::json!({}))
-        .expect("failed to call fixedString");
+    let ctx = client();
 
-    // Get the string value using the Response.get_string method
-    let str_value = response
-        .get_string("")
-        .expect("failed to get string from response");
+    let str_value = debug::fixed_string(&ctx).expect("failed to call fixedString");
 
     assert!(
         !str_value.is_empty(),
@@ -44,9 +64,9 @@ fn test_fixed_string() {
 }
 
 #[test]
-#[ignore]
+#[cfg_attr(not(feature = "record-replay"), ignore)]
 fn test_error() {
-    let ctx = Client::new();
+    let ctx = client();
 
     let result = ctx.do_request("Misc/Debug:error", "GET", serde_json::json!({}));
 
@@ -62,9 +82,9 @@ fn test_error() {
 }
 
 #[test]
-#[ignore]
+#[cfg_attr(not(feature = "record-replay"), ignore)]
 fn test_error_unwrap() {
-    let ctx = Client::new();
+    let ctx = client();
 
     // Test with the fieldError endpoint
     let result = ctx.do_request("Misc/Debug:fieldError", "GET", serde_json::json!({"i": 42}));
@@ -84,9 +104,9 @@ fn test_error_unwrap() {
 }
 
 #[test]
-#[ignore]
+#[cfg_attr(not(feature = "record-replay"), ignore)]
 fn test_redirect() {
-    let ctx = Client::new();
+    let ctx = client();
 
     let result = ctx.do_request("Misc/Debug:testRedirect", "GET", serde_json::json!({}));
 
@@ -102,25 +122,15 @@ fn test_redirect() {
 }
 
 #[test]
-#[ignore]
+#[cfg_attr(not(feature = "record-replay"), ignore)]
 fn test_argument() {
-    let ctx = Client::new();
+    let ctx = client();
 
     let test_value = "hello world";
 
-    // Test with the required 'input' parameter
-    let response = ctx
-        .do_request(
-            "Misc/Debug:argument",
-            "GET",
-            serde_json::json!({"input": test_value}),
-        )
-        .expect("failed to call argument endpoint");
-
     // The endpoint should return our input value
-    let returned_value = response
-        .get_string("input")
-        .expect("failed to get input value from response");
+    let returned_value =
+        debug::echo_argument(&ctx, test_value).expect("failed to call argument endpoint");
 
     assert_eq!(
         returned_value, test_value,
@@ -132,26 +142,14 @@ fn test_argument() {
 }
 
 #[test]
-#[ignore]
+#[cfg_attr(not(feature = "record-replay"), ignore)]
 fn test_arg_string() {
-    let ctx = Client::new();
+    let ctx = client();
 
     let test_value = "test string";
 
-    // Using apply to unmarshal directly into a map
-    let result: HashMap<String, serde_json::Value> = ctx
-        .apply(
-            "Misc/Debug:argString",
-            "GET",
-            serde_json::json!({"input_string": test_value}),
-        )
-        .expect("failed to call argString endpoint");
-
     // The endpoint should echo the input_string in the response
-    let returned = result
-        .get("input_string")
-        .and_then(|v| v.as_str())
-        .expect("expected input_string in response");
+    let returned = debug::echo_string(&ctx, test_value).expect("failed to call argString endpoint");
 
     assert_eq!(
         returned, test_value,
@@ -163,7 +161,6 @@ fn test_arg_string() {
 }
 
 #[test]
-#[ignore]
 fn test_response_as() {
     // Define a struct that matches our expected data structure
     #[derive(Debug, Deserialize)]